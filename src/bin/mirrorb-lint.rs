@@ -0,0 +1,59 @@
+// checks a levels.dat file without starting the game
+//
+//   mirrorb-lint [path to levels.dat]
+
+use mirrorb::levels;
+
+use std::collections::HashMap;
+use std::process::ExitCode;
+
+
+fn main() -> ExitCode {
+
+    let args: Vec<String> = std::env::args().collect();
+    let filename = match args.get(1) {
+        Some(arg) => arg.as_str(),
+        None => "assets/levels.dat",
+    };
+
+    let blocks = match levels::read_blocks(filename) {
+        Ok(blocks) => blocks,
+        Err(err) => {
+            eprintln!("{filename}: {err}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut nmaps = 0;
+    let mut nproblems = 0;
+    let mut seen: HashMap<Vec<u8>, usize> = HashMap::new();
+
+    for (lineno, block) in blocks {
+        nmaps += 1;
+
+        let block = match block {
+            Ok(block) => block,
+            Err(err) => {
+                println!("{filename}:{lineno}: {err}");
+                nproblems += 1;
+                continue;
+            }
+        };
+
+        let mut problems = levels::lint_block(&block);
+
+        match seen.get(&block.map) {
+            Some(first) => problems.push(format!("duplicate of the map on line {first}")),
+            None => { seen.insert(block.map.clone(), lineno); }
+        }
+
+        for p in &problems {
+            println!("{filename}:{lineno}: {p}");
+        }
+        nproblems += problems.len();
+    }
+
+    println!("{nmaps} maps, {nproblems} problems");
+
+    if 0 == nproblems { ExitCode::SUCCESS } else { ExitCode::FAILURE }
+}
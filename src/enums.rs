@@ -0,0 +1,142 @@
+#![allow(dead_code)]
+
+pub const SCREEN_XRES: usize = 368;
+pub const SCREEN_XRES_HALF: usize = SCREEN_XRES / 2;
+pub const SCREEN_YRES: usize = 264;
+pub const SCREEN_YRES_HALF: usize = SCREEN_YRES / 2;
+pub const SCREEN_Y_OFFSET: f32 = 24.0;
+
+pub const TILE_SZ: usize = 16;
+pub const TILE_SZ_HALF: usize = TILE_SZ / 2;
+pub const TILE_SZ_QTR: usize = TILE_SZ / 4;
+
+pub const MAP_MAX_WIDTH: usize = 60;
+pub const MAP_MAX_HEIGHT: usize = MAP_MAX_WIDTH;
+pub const MAP_VIEW_WIDTH: usize = 15; // widest board drawn with full size tiles
+
+pub const MAX_PIECES: usize = 24;
+
+pub const SPLIT_ROWS: usize = 5; // most recent speedrun splits on screen
+pub const SELECT_GROUPS: usize = 16; // level groups per level select page, two columns
+
+pub const CLIPPING_NONE: u8 = 0;
+pub const CLIPPING_SOURCE: u8 = 1;
+
+pub const REFLECTOR_INVALID: u16 = 0;
+pub const REFLECTOR_TL: u16 = 17;
+pub const REFLECTOR_TR: u16 = 18;
+pub const REFLECTOR_BL: u16 = 19;
+pub const REFLECTOR_BR: u16 = 20;
+pub const REFLECTOR_D: u16 = 21;
+pub const REFLECTOR_L: u16 = 22;
+pub const REFLECTOR_U: u16 = 23;
+pub const REFLECTOR_R: u16 = 24;
+
+// pieces the level locks in place, same order as REFLECTOR_TL..REFLECTOR_R.
+// they sit past the last tinted copy, beam::tile_color reads them as white
+pub const REFLECTOR_LOCKED: u16 = 161;
+
+pub const SOURCE_U: u16 = 33;
+pub const SOURCE_D: u16 = 34;
+pub const SOURCE_L: u16 = 35;
+pub const SOURCE_R: u16 = 36;
+
+pub const BURST: u16 = 57;
+
+pub const BEAM_H: u16 = 41;
+pub const BEAM_V: u16 = 42;
+pub const BEAM_STOP_U: u16 = 43;
+pub const BEAM_STOP_D: u16 = 44;
+pub const BEAM_STOP_L: u16 = 45;
+pub const BEAM_STOP_R: u16 = 46;
+
+pub const BEAM_SPLIT_INVALID: u16 = 0;
+pub const BEAM_SPLIT_TL: u16 = 49;
+pub const BEAM_SPLIT_TR: u16 = 50;
+pub const BEAM_SPLIT_BL: u16 = 51;
+pub const BEAM_SPLIT_BR: u16 = 52;
+pub const BEAM_SPLIT_D: u16 = 53;
+pub const BEAM_SPLIT_L: u16 = 54;
+pub const BEAM_SPLIT_U: u16 = 55;
+pub const BEAM_SPLIT_R: u16 = 56;
+pub const BEAM_SPLIT_X: u16 = 47;
+
+// beam colors, white is the plain beam every source fired before colors
+pub const COLOR_WHITE: u8 = 0;
+pub const COLOR_RED: u8 = 1;
+pub const COLOR_GREEN: u8 = 2;
+pub const COLOR_BLUE: u8 = 3;
+pub const NUM_COLORS: u8 = 4;
+pub const COLOR_INVALID: u8 = 255;
+
+// sources, beams and the tiles below come in a tinted copy per color,
+// COLOR_TILE_OFFSET apart in the tileset
+pub const COLOR_TILE_OFFSET: u16 = 32;
+pub const COLOR_TILE_LAST: u16 = SOURCE_U + COLOR_TILE_OFFSET - 1; // last white tile with tinted copies
+pub const ORB_TINTED: u16 = 58;
+pub const ORB_TINTED_ACTIVE: u16 = 59;
+pub const FILTER: u16 = 60;
+
+pub const ORB_RED: u16 = ORB_TINTED + COLOR_TILE_OFFSET;
+pub const ORB_GREEN: u16 = ORB_TINTED + COLOR_TILE_OFFSET * 2;
+pub const ORB_BLUE: u16 = ORB_TINTED + COLOR_TILE_OFFSET * 3;
+pub const FILTER_RED: u16 = FILTER + COLOR_TILE_OFFSET;
+pub const FILTER_GREEN: u16 = FILTER + COLOR_TILE_OFFSET * 2;
+pub const FILTER_BLUE: u16 = FILTER + COLOR_TILE_OFFSET * 3;
+
+// linked portal pairs, each pair has its own tile
+pub const PORTAL: u16 = 9;
+pub const NUM_PORTAL_PAIRS: usize = 4;
+
+pub const GRID: u16 = 2;
+pub const FLOOR: u16 = 3;
+pub const FLOOR_ALT: u16 = 4; // the other floor tile of the checkerboard
+pub const BLOCK: u16 = 5;
+pub const ORB: u16 = 6;
+pub const ORB_ACTIVE: u16 = 7;
+
+// levels.dat map cells other than orbs, filters, portals and locked pieces.
+// a path cell is floor the intended beam crosses
+pub const CELL_FLOOR: u8 = b'.';
+pub const CELL_PATH: u8 = b'x';
+pub const CELL_ORB: u8 = b'o';
+
+// piece index sentinels, kept clear of 0..MAX_PIECES so the cap can grow
+pub const HOLDING_INVALID: u8 = u8::MAX;
+pub const INVENTORY_IDX_INVALID: u8 = u8::MAX;
+pub const BOARD_IDX_INVALID: u16 = u16::MAX;
+
+pub const MENU_HOVER_INVALID: u8 = 255;
+pub const MENU_HOVER_UNDO: u8 = 0;
+pub const MENU_HOVER_REDO: u8 = 1;
+pub const MENU_HOVER_ROTATE: u8 = 2;
+pub const MENU_HOVER_FLIP_H: u8 = 3;
+pub const MENU_HOVER_FLIP_V: u8 = 4;
+pub const MENU_HOVER_NEXT: u8 = 5;
+pub const MENU_HOVER_RESET: u8 = 6;
+pub const MENU_HOVER_TRASH: u8 = 7;
+pub const MENU_HOVER_HINT: u8 = 8;
+
+// input actions, see ui::bind_actions
+pub const ACTION_QUIT: u8 = 0;
+pub const ACTION_DEBUG: u8 = 1;
+pub const ACTION_EXPORT: u8 = 2;
+pub const ACTION_EXPORT_SOLUTION: u8 = 3;
+pub const ACTION_IMPORT: u8 = 4;
+pub const ACTION_CLICK: u8 = 5;
+pub const ACTION_FIRE: u8 = 6;
+pub const ACTION_CURSOR_UP: u8 = 7;
+pub const ACTION_CURSOR_DOWN: u8 = 8;
+pub const ACTION_CURSOR_LEFT: u8 = 9;
+pub const ACTION_CURSOR_RIGHT: u8 = 10;
+pub const ACTION_UNDO: u8 = 11;
+pub const ACTION_REDO: u8 = 12;
+pub const ACTION_ROTATE: u8 = 13;
+pub const ACTION_FLIP_H: u8 = 14;
+pub const ACTION_FLIP_V: u8 = 15;
+pub const ACTION_RESET: u8 = 16;
+pub const ACTION_NEW_BOARD: u8 = 17;
+pub const ACTION_HINT: u8 = 18;
+pub const ACTION_BRANCH_PREV: u8 = 19;
+pub const ACTION_BRANCH_NEXT: u8 = 20;
+pub const ACTION_RETURN_PIECE: u8 = 21;
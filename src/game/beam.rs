@@ -0,0 +1,792 @@
+use super::enums::*;
+use super::game_state::PieceData;
+use super::scene;
+use super::scene::SceneData;
+use super::scene::TileMap;
+
+use std::collections::VecDeque;
+
+
+// result of tracing a single beam source to completion
+#[derive(Default, Clone)]
+pub struct BeamTrace {
+    pub beams: TileMap,
+    pub colors: Vec<u8>, // color each lit cell is drawn in
+    pub path: Vec<(usize, u16)>, // (board idx, beam tile) in the order cells were lit
+    pub lit_orbs: Vec<usize>,
+}
+
+
+pub fn is_source(tile: u16) -> bool {
+    let tile = untinted(tile);
+    SOURCE_U == tile || SOURCE_D == tile || SOURCE_L == tile || SOURCE_R == tile
+}
+
+pub fn is_orb(tile: u16) -> bool {
+    ORB == tile || (COLOR_WHITE != tile_color(tile) && ORB_TINTED == untinted(tile))
+}
+
+pub fn is_filter(tile: u16) -> bool {
+    COLOR_WHITE != tile_color(tile) && FILTER == untinted(tile)
+}
+
+pub fn is_portal(tile: u16) -> bool {
+    (PORTAL..PORTAL + NUM_PORTAL_PAIRS as u16).contains(&tile)
+}
+
+
+// color of a tinted tile, white for everything else
+pub fn tile_color(tile: u16) -> u8 {
+    if !(SOURCE_U + COLOR_TILE_OFFSET..SOURCE_U + COLOR_TILE_OFFSET * NUM_COLORS as u16).contains(&tile) { return COLOR_WHITE; }
+    ((tile - SOURCE_U) / COLOR_TILE_OFFSET) as u8
+}
+
+// the white tile a tinted one was made from
+pub fn untinted(tile: u16) -> u16 {
+    tile - COLOR_TILE_OFFSET * tile_color(tile) as u16
+}
+
+// tile as drawn in a color, for sources, beams and lit orbs
+pub fn tinted(tile: u16, color: u8) -> u16 {
+    if COLOR_WHITE == color || COLOR_INVALID == color { return tile; }
+    if ORB_ACTIVE == tile { return ORB_TINTED_ACTIVE + COLOR_TILE_OFFSET * color as u16; }
+    if !(SOURCE_U..=COLOR_TILE_LAST).contains(&tile) { return tile; }
+    tile + COLOR_TILE_OFFSET * color as u16
+}
+
+// beams map with every lit cell in its color
+pub fn tinted_beams(beams: &TileMap, colors: &[u8]) -> TileMap {
+    let mut ret = beams.clone();
+    if colors.len() != beams.len() { return ret; }
+    for i in 0..ret.len() {
+        ret[i] = tinted(ret[i], colors[i]);
+    }
+    ret
+}
+
+
+// every edge cell a beam can be fired from
+pub fn sources(scene: &SceneData) -> Vec<usize> {
+    let mut ret = Vec::new();
+    for i in 0..scene.sz2 {
+        if is_source(scene.tilemap[i]) {
+            ret.push(i);
+        }
+    }
+    ret
+}
+
+
+// propagate the beam fired from source_idx until nothing else can light up
+pub fn trace(scene: &SceneData, pieces: &[PieceData], source_idx: usize) -> BeamTrace {
+
+    let sz = scene.sz;
+    let sz2 = scene.sz2;
+    let clipping = scene::build_clipping(scene, pieces);
+
+    let mut ret = BeamTrace {
+        beams: vec![0; sz2],
+        colors: vec![COLOR_WHITE; sz2],
+        path: Vec::new(),
+        lit_orbs: Vec::new(),
+    };
+
+    let tile = scene.tilemap[source_idx];
+    if !is_source(tile) { return ret; }
+
+    // colors of the horizontal and vertical beam through each cell,
+    // boards without any tinted tiles keep every beam white
+    let tracking = scene.tilemap.iter().any(|t| COLOR_WHITE != tile_color(*t));
+    let mut axis = vec![[COLOR_WHITE; 2]; sz2];
+    let color = tile_color(tile);
+    axis[source_idx] = [color, color];
+    ret.colors[source_idx] = color;
+
+    // the other end of each portal and the sides a portal sends a beam out of,
+    // both left empty on boards without portals
+    let mut pair: Vec<Option<usize>> = Vec::new();
+    let mut exits: Vec<[bool; 4]> = Vec::new();
+    if !scene.portals.is_empty() {
+        pair = vec![None; sz2];
+        exits = vec![[false; 4]; sz2];
+        for &(a, b) in &scene.portals {
+            pair[a] = Some(b);
+            pair[b] = Some(a);
+        }
+    }
+
+    ret.beams[source_idx] = BEAM_STOP_U + (untinted(tile) - SOURCE_U);
+    ret.path.push((source_idx, ret.beams[source_idx]));
+
+    // re-evaluate the neighbours of every cell that changes
+    let mut queue: VecDeque<usize> = VecDeque::new();
+    push_neighbors(&mut queue, source_idx, sz);
+
+    while let Some(idx) = queue.pop_front() {
+        // orbs and filters need the incoming colors up front, other cells only once they change
+        let clipped = clipping[idx] as u16;
+        let colored = CLIPPING_NONE != clipped as u8 && (is_orb(clipped) || is_filter(clipped));
+        let portal = !exits.is_empty() && CLIPPING_NONE != clipped as u8 && is_portal(clipped);
+        let mut inc = [COLOR_INVALID; 4];
+        if colored { inc = incoming(&ret.beams, &clipping, &exits, &axis, sz, source_idx, idx); }
+
+        let tile = next_tile(&ret.beams, &clipping, &exits, &inc, sz, source_idx, idx);
+        if 255 != tile {
+            let prev = ret.beams[idx];
+
+            if portal {
+                // a beam going into a portal comes out of its pair heading the same way,
+                // the one step here that isn't between neighbours
+                let inc = incoming(&ret.beams, &clipping, &exits, &axis, sz, source_idx, idx);
+                let entries = portal_entries(&ret.beams, &clipping, &exits, sz, source_idx, idx);
+                let mut color = COLOR_INVALID;
+                for side in 0..4 {
+                    if exits[idx][side] { color = axis[idx][axis_of(side)]; }
+                }
+                for side in 0..4 {
+                    if !entries[side] { continue; }
+                    color = inc[side];
+
+                    let dir = opposite(side);
+                    if let Some(p) = pair[idx] {
+                        if !exits[p][dir] {
+                            exits[p][dir] = true;
+                            axis[p][axis_of(dir)] = inc[side];
+                            queue.push_back(p);
+                            push_neighbors(&mut queue, p, sz);
+                        }
+                    }
+                }
+                if tracking { ret.colors[idx] = color; }
+
+            } else {
+                if tracking {
+                    if !colored { inc = incoming(&ret.beams, &clipping, &exits, &axis, sz, source_idx, idx); }
+                    axis[idx] = next_colors(prev, tile, clipped, &inc, axis[idx]);
+                }
+                if tracking && (0 == prev || is_stop(prev)) {
+                    ret.colors[idx] = match tile {
+                        BEAM_V => axis[idx][1],
+                        ORB_ACTIVE => tile_color(clipped),
+                        _ => axis[idx][0],
+                    };
+                }
+            }
+
+            ret.beams[idx] = tile;
+            ret.path.push((idx, tile));
+            push_neighbors(&mut queue, idx, sz);
+        }
+    }
+
+    for i in 0..sz2 {
+        if ORB_ACTIVE == ret.beams[i] {
+            ret.lit_orbs.push(i);
+        }
+    }
+
+    ret
+}
+
+
+fn push_neighbors(queue: &mut VecDeque<usize>, idx: usize, sz: usize) {
+    let x = idx % sz;
+    let y = idx / sz;
+    if 0 < y { queue.push_back(idx - sz); }
+    if sz - 1 > y { queue.push_back(idx + sz); }
+    if 0 < x { queue.push_back(idx - 1); }
+    if sz - 1 > x { queue.push_back(idx + 1); }
+}
+
+
+// new beam tile for an interior cell given its neighbours, or 255 if unchanged
+#[rustfmt::skip]
+fn next_tile(beams: &TileMap, clipping: &scene::ClippingMap, exits: &[[bool; 4]], inc: &[u8; 4], sz: usize, orig: usize, idx: usize) -> u16 {
+
+    let x = idx % sz;
+    let y = idx / sz;
+    if 0 == x || 0 == y || sz - 1 == x || sz - 1 == y { return 255; }
+
+    let mut tile = 255;
+    let up = idx - sz;
+    let down = idx + sz;
+    let left = idx - 1;
+    let right = idx + 1;
+
+    let beam_up = feed(beams, clipping, exits, up, 1);
+    let beam_down = feed(beams, clipping, exits, down, 0);
+    let beam_left = feed(beams, clipping, exits, left, 3);
+    let beam_right = feed(beams, clipping, exits, right, 2);
+
+    let clipped = clipping[idx] as u16;
+
+    // portals draw every side a beam goes in or out of
+    if !exits.is_empty() && CLIPPING_NONE != clipped as u8 && is_portal(clipped) {
+        let mut arms = portal_entries(beams, clipping, exits, sz, orig, idx);
+        for side in 0..4 {
+            arms[side] |= exits[idx][side];
+        }
+        tile = portal_tile(arms);
+        if tile == beams[idx] { return 255; }
+        return tile;
+    }
+
+    let colored = CLIPPING_NONE != clipped as u8 && (is_orb(clipped) || is_filter(clipped));
+
+    if 0 != beams[idx] {
+
+        // an orb or filter that stopped a beam of the wrong color can still take one that fits
+        if colored && is_stop(beams[idx]) {
+            return colored_tile(clipped, inc);
+        }
+
+        // filters only cross beams they let through
+        if is_filter(clipped) && BEAM_SPLIT_X != colored_tile(clipped, inc) {
+            return tile;
+        }
+
+        // only allow crossover
+        if BEAM_H == beams[idx] {
+            // anything above or below going vertical?
+            if BEAM_V == beam_up || BEAM_V == beam_down ||
+               BEAM_SPLIT_TL == beam_up || BEAM_SPLIT_TR == beam_up ||
+               BEAM_SPLIT_BL == beam_down || BEAM_SPLIT_BR == beam_down ||
+               BEAM_SPLIT_L == beam_up || BEAM_SPLIT_R == beam_up ||
+               BEAM_SPLIT_L == beam_down || BEAM_SPLIT_R == beam_down ||
+               BEAM_SPLIT_X == beam_up || BEAM_SPLIT_X == beam_down ||
+               orig == up || orig == down
+            {
+                tile = BEAM_SPLIT_X;
+            }
+
+        } else if BEAM_V == beams[idx] {
+            if BEAM_H == beam_left || BEAM_H == beam_right ||
+               BEAM_SPLIT_TL == beam_left || BEAM_SPLIT_BL == beam_left ||
+               BEAM_SPLIT_TR == beam_right || BEAM_SPLIT_BR == beam_right ||
+               BEAM_SPLIT_U == beam_left || BEAM_SPLIT_U == beam_right ||
+               BEAM_SPLIT_D == beam_left || BEAM_SPLIT_D == beam_right ||
+               BEAM_SPLIT_X == beam_left || BEAM_SPLIT_X == beam_right ||
+               orig == left || orig == right
+            {
+                tile = BEAM_SPLIT_X;
+            }
+        }
+
+        return tile;
+    }
+
+    if CLIPPING_NONE == clipped as u8 {
+        // check for next to source
+        if up == orig || down == orig { tile = BEAM_V; }
+        if left == orig || right == orig { tile = BEAM_H; }
+
+        if BEAM_V == beam_up || BEAM_V == beam_down || BEAM_SPLIT_X == beam_up || BEAM_SPLIT_X == beam_down {
+            tile = BEAM_V;
+        }
+
+        if BEAM_H == beam_left || BEAM_H == beam_right || BEAM_SPLIT_X == beam_left || BEAM_SPLIT_X == beam_right {
+            tile = BEAM_H;
+        }
+
+        if BEAM_SPLIT_BL == beam_left || BEAM_SPLIT_TL == beam_left || BEAM_SPLIT_BR == beam_right || BEAM_SPLIT_TR == beam_right ||
+           BEAM_SPLIT_U == beam_left || BEAM_SPLIT_D == beam_left || BEAM_SPLIT_U == beam_right || BEAM_SPLIT_D == beam_right {
+            tile = BEAM_H;
+        }
+
+        if BEAM_SPLIT_BL == beam_down || BEAM_SPLIT_TL == beam_up || BEAM_SPLIT_BR == beam_down || BEAM_SPLIT_TR == beam_up ||
+           BEAM_SPLIT_L == beam_up || BEAM_SPLIT_R == beam_up || BEAM_SPLIT_L == beam_down || BEAM_SPLIT_R == beam_down {
+            tile = BEAM_V;
+        }
+
+    // blocks, and orbs or filters hit by the wrong color
+    } else if BLOCK == clipped || (colored && 255 == colored_tile(clipped, inc)) {
+        if BEAM_V == beam_up ||
+           BEAM_SPLIT_X == beam_up ||
+           BEAM_SPLIT_TL == beam_up ||
+           BEAM_SPLIT_TR == beam_up ||
+           BEAM_SPLIT_L == beam_up ||
+           BEAM_SPLIT_R == beam_up ||
+           orig == up {
+            tile = BEAM_STOP_U;
+        }
+        if BEAM_V == beam_down ||
+           BEAM_SPLIT_X == beam_down ||
+           BEAM_SPLIT_BL == beam_down ||
+           BEAM_SPLIT_BR == beam_down ||
+           BEAM_SPLIT_L == beam_down ||
+           BEAM_SPLIT_R == beam_down ||
+           orig == down {
+            tile = BEAM_STOP_D;
+        }
+        if BEAM_H == beam_left ||
+           BEAM_SPLIT_X == beam_left ||
+           BEAM_SPLIT_TL == beam_left ||
+           BEAM_SPLIT_BL == beam_left ||
+           BEAM_SPLIT_U == beam_left ||
+           BEAM_SPLIT_D == beam_left ||
+           orig == left {
+            tile = BEAM_STOP_L;
+        }
+        if BEAM_H == beam_right ||
+           BEAM_SPLIT_X == beam_right ||
+           BEAM_SPLIT_BR == beam_right ||
+           BEAM_SPLIT_TR == beam_right ||
+           BEAM_SPLIT_U == beam_right ||
+           BEAM_SPLIT_D == beam_right ||
+           orig == right {
+            tile = BEAM_STOP_R;
+        }
+
+    // orbs and filters
+    } else if colored {
+        tile = colored_tile(clipped, inc);
+
+    // reflectors
+    } else if REFLECTOR_BL == clipped {
+        if BEAM_V == beam_up || BEAM_H == beam_right ||
+            BEAM_SPLIT_BR == beam_right ||
+            BEAM_SPLIT_TR == beam_right ||
+            BEAM_SPLIT_TR == beam_up ||
+            BEAM_SPLIT_TL == beam_up ||
+            BEAM_SPLIT_D == beam_right ||
+            BEAM_SPLIT_U == beam_right ||
+            BEAM_SPLIT_L == beam_up ||
+            BEAM_SPLIT_R == beam_up ||
+            BEAM_SPLIT_X == beam_up || BEAM_SPLIT_X == beam_right ||
+            orig == up || orig == right {
+            tile = BEAM_SPLIT_BL;
+        }
+
+    } else if REFLECTOR_BR == clipped {
+        if BEAM_V == beam_up || BEAM_H == beam_left ||
+            BEAM_SPLIT_BL == beam_left ||
+            BEAM_SPLIT_TL == beam_left ||
+            BEAM_SPLIT_TR == beam_up ||
+            BEAM_SPLIT_TL == beam_up ||
+            BEAM_SPLIT_D == beam_left ||
+            BEAM_SPLIT_U == beam_left ||
+            BEAM_SPLIT_L == beam_up ||
+            BEAM_SPLIT_R == beam_up ||
+            BEAM_SPLIT_X == beam_up || BEAM_SPLIT_X == beam_left ||
+            orig == up || orig == left {
+            tile = BEAM_SPLIT_BR;
+        }
+
+    } else if REFLECTOR_TL == clipped {
+        if BEAM_V == beam_down || BEAM_H == beam_right ||
+            BEAM_SPLIT_BR == beam_right ||
+            BEAM_SPLIT_TR == beam_right ||
+            BEAM_SPLIT_BL == beam_down ||
+            BEAM_SPLIT_BR == beam_down ||
+            BEAM_SPLIT_D == beam_right ||
+            BEAM_SPLIT_U == beam_right ||
+            BEAM_SPLIT_L == beam_down ||
+            BEAM_SPLIT_R == beam_down ||
+            BEAM_SPLIT_X == beam_down || BEAM_SPLIT_X == beam_right ||
+            orig == down || orig == right {
+            tile = BEAM_SPLIT_TL;
+        }
+
+    } else if REFLECTOR_TR == clipped {
+        if BEAM_V == beam_down || BEAM_H == beam_left ||
+            BEAM_SPLIT_BL == beam_left ||
+            BEAM_SPLIT_TL == beam_left ||
+            BEAM_SPLIT_BR == beam_down ||
+            BEAM_SPLIT_BL == beam_down ||
+            BEAM_SPLIT_D == beam_left ||
+            BEAM_SPLIT_U == beam_left ||
+            BEAM_SPLIT_L == beam_down ||
+            BEAM_SPLIT_R == beam_down ||
+            BEAM_SPLIT_X == beam_down || BEAM_SPLIT_X == beam_left ||
+            orig == down || orig == left {
+            tile = BEAM_SPLIT_TR;
+        }
+
+    } else if REFLECTOR_D == clipped {
+        if BEAM_V == beam_up ||
+           BEAM_SPLIT_TR == beam_up ||
+           BEAM_SPLIT_TL == beam_up ||
+           BEAM_SPLIT_L == beam_up ||
+           BEAM_SPLIT_R == beam_up ||
+           BEAM_SPLIT_X == beam_up ||
+           orig == up {
+            tile = BEAM_SPLIT_D;
+        }
+
+    } else if REFLECTOR_L == clipped {
+        if BEAM_H == beam_right ||
+           BEAM_SPLIT_BR == beam_right ||
+           BEAM_SPLIT_TR == beam_right ||
+           BEAM_SPLIT_U == beam_right ||
+           BEAM_SPLIT_D == beam_right ||
+           BEAM_SPLIT_X == beam_right ||
+           orig == right {
+            tile = BEAM_SPLIT_L;
+        }
+
+    } else if REFLECTOR_U == clipped {
+        if BEAM_V == beam_down ||
+           BEAM_SPLIT_BR == beam_down ||
+           BEAM_SPLIT_BL == beam_down ||
+           BEAM_SPLIT_L == beam_down ||
+           BEAM_SPLIT_R == beam_down ||
+           BEAM_SPLIT_X == beam_down ||
+           orig == down {
+            tile = BEAM_SPLIT_U;
+        }
+
+    } else if REFLECTOR_R == clipped {
+        if BEAM_H == beam_left ||
+           BEAM_SPLIT_BL == beam_left ||
+           BEAM_SPLIT_TL == beam_left ||
+           BEAM_SPLIT_U == beam_left ||
+           BEAM_SPLIT_D == beam_left ||
+           BEAM_SPLIT_X == beam_left ||
+           orig == left {
+            tile = BEAM_SPLIT_R;
+        }
+
+    }
+
+    tile
+}
+
+
+// directions are 0 up, 1 down, 2 left, 3 right
+pub fn step(idx: usize, dir: usize, sz: usize) -> Option<usize> {
+    let x = idx % sz;
+    let y = idx / sz;
+    match dir {
+        0 if 0 < y => Some(idx - sz),
+        1 if sz - 1 > y => Some(idx + sz),
+        2 if 0 < x => Some(idx - 1),
+        3 if sz - 1 > x => Some(idx + 1),
+        _ => None,
+    }
+}
+
+pub fn opposite(dir: usize) -> usize {
+    match dir {
+        0 => 1,
+        1 => 0,
+        2 => 3,
+        _ => 2,
+    }
+}
+
+// sides a reflector can be lit from and the sides it sends the beam out of
+pub fn reflector_arms(tile: u16) -> ([bool; 4], [bool; 4]) {
+    match tile {
+        REFLECTOR_BL => ([true, false, false, true], [true, false, false, true]),
+        REFLECTOR_BR => ([true, false, true, false], [true, false, true, false]),
+        REFLECTOR_TL => ([false, true, false, true], [false, true, false, true]),
+        REFLECTOR_TR => ([false, true, true, false], [false, true, true, false]),
+        REFLECTOR_D => ([true, false, false, false], [false, false, true, true]),
+        REFLECTOR_L => ([false, false, false, true], [true, true, false, false]),
+        REFLECTOR_U => ([false, true, false, false], [false, false, true, true]),
+        REFLECTOR_R => ([false, false, true, false], [true, true, false, false]),
+        _ => ([false; 4], [false; 4]),
+    }
+}
+
+// sides a lit cell sends its beam out of
+fn beam_arms(tile: u16) -> [bool; 4] {
+    match tile {
+        BEAM_H | BEAM_SPLIT_D | BEAM_SPLIT_U => [false, false, true, true],
+        BEAM_V | BEAM_SPLIT_L | BEAM_SPLIT_R => [true, true, false, false],
+        BEAM_SPLIT_X => [true; 4],
+        BEAM_SPLIT_TL => [false, true, false, true],
+        BEAM_SPLIT_TR => [false, true, true, false],
+        BEAM_SPLIT_BL => [true, false, false, true],
+        BEAM_SPLIT_BR => [true, false, true, false],
+        _ => [false; 4],
+    }
+}
+
+fn is_stop(tile: u16) -> bool {
+    (BEAM_STOP_U..=BEAM_STOP_R).contains(&tile)
+}
+
+// up and down feed the vertical beam through a cell, left and right the horizontal
+fn axis_of(side: usize) -> usize {
+    if 2 > side { 1 } else { 0 }
+}
+
+
+// beam tile at n as seen from the cell it would light going in dir,
+// portals only light the sides their pair sends a beam out of
+fn feed(beams: &TileMap, clipping: &scene::ClippingMap, exits: &[[bool; 4]], n: usize, dir: usize) -> u16 {
+    if exits.is_empty() { return beams[n]; }
+    let clipped = clipping[n] as u16;
+    if CLIPPING_NONE == clipped as u8 || !is_portal(clipped) { return beams[n]; }
+    if !exits[n][dir] { return 0; }
+    if 2 > dir { BEAM_V } else { BEAM_H }
+}
+
+// sides a beam comes into the portal at idx from, leaving out the ones it sends beams out of
+fn portal_entries(beams: &TileMap, clipping: &scene::ClippingMap, exits: &[[bool; 4]], sz: usize, orig: usize, idx: usize) -> [bool; 4] {
+    let next = [idx - sz, idx + sz, idx - 1, idx + 1];
+
+    let mut ret = [false; 4];
+    for side in 0..4 {
+        if exits[idx][side] { continue; }
+        let n = next[side];
+        let dir = opposite(side);
+        ret[side] = orig == n || beam_arms(feed(beams, clipping, exits, n, dir))[dir];
+    }
+    ret
+}
+
+// half a beam from the middle of the cell out to each lit side
+#[rustfmt::skip]
+fn portal_tile(arms: [bool; 4]) -> u16 {
+    match arms {
+        [false, false, false, false] => 0,
+        [true,  false, false, false] => BEAM_STOP_U,
+        [false, true,  false, false] => BEAM_STOP_D,
+        [false, false, true,  false] => BEAM_STOP_L,
+        [false, false, false, true ] => BEAM_STOP_R,
+        [true,  true,  false, false] => BEAM_V,
+        [false, false, true,  true ] => BEAM_H,
+        [false, true,  false, true ] => BEAM_SPLIT_TL,
+        [false, true,  true,  false] => BEAM_SPLIT_TR,
+        [true,  false, false, true ] => BEAM_SPLIT_BL,
+        [true,  false, true,  false] => BEAM_SPLIT_BR,
+        [true,  false, true,  true ] => BEAM_SPLIT_D,
+        [false, true,  true,  true ] => BEAM_SPLIT_U,
+        [true,  true,  false, true ] => BEAM_SPLIT_L,
+        [true,  true,  true,  false] => BEAM_SPLIT_R,
+        _ => BEAM_SPLIT_X,
+    }
+}
+
+
+// color of the beam coming into idx from each side, COLOR_INVALID where none does
+fn incoming(beams: &TileMap, clipping: &scene::ClippingMap, exits: &[[bool; 4]], axis: &[[u8; 2]], sz: usize, orig: usize, idx: usize) -> [u8; 4] {
+    let x = idx % sz;
+    let y = idx / sz;
+    let inside = [0 < y, sz - 1 > y, 0 < x, sz - 1 > x];
+    let next = [idx.wrapping_sub(sz), idx + sz, idx.wrapping_sub(1), idx + 1];
+
+    let mut ret = [COLOR_INVALID; 4];
+    for side in 0..4 {
+        if !inside[side] { continue; }
+        let n = next[side];
+        let dir = opposite(side);
+        if orig == n || beam_arms(feed(beams, clipping, exits, n, dir))[dir] {
+            ret[side] = axis[n][axis_of(side)];
+        }
+    }
+    ret
+}
+
+
+// what an orb or filter does with the beams coming in, 255 if it takes none of them
+fn colored_tile(clipped: u16, inc: &[u8; 4]) -> u16 {
+    let color = tile_color(clipped);
+    let fits = |c: u8| COLOR_INVALID != c && (COLOR_WHITE == color || c == color);
+
+    if is_orb(clipped) {
+        if inc.iter().any(|c| fits(*c)) { return ORB_ACTIVE; }
+        return 255;
+    }
+
+    // a filter recolors white light and lets its own color through
+    let fits = |c: u8| COLOR_INVALID != c && (COLOR_WHITE == c || c == color);
+    let h = fits(inc[2]) || fits(inc[3]);
+    let v = fits(inc[0]) || fits(inc[1]);
+    match (h, v) {
+        (true, true) => BEAM_SPLIT_X,
+        (true, false) => BEAM_H,
+        (false, true) => BEAM_V,
+        _ => 255,
+    }
+}
+
+
+// horizontal and vertical beam colors for a cell that just changed from prev to tile
+fn next_colors(prev: u16, tile: u16, clipped: u16, inc: &[u8; 4], axis: [u8; 2]) -> [u8; 2] {
+    let first = |a: usize, b: usize| if COLOR_INVALID != inc[a] { inc[a] } else { inc[b] };
+
+    if is_filter(clipped) && !is_stop(tile) {
+        let color = tile_color(clipped);
+        return [color, color];
+    }
+
+    match tile {
+        BEAM_H => [first(2, 3), axis[1]],
+        BEAM_V => [axis[0], first(0, 1)],
+        BEAM_SPLIT_X if BEAM_H == prev => [axis[0], first(0, 1)],
+        BEAM_SPLIT_X if BEAM_V == prev => [first(2, 3), axis[1]],
+        BEAM_SPLIT_X => [first(2, 3), first(0, 1)],
+        BEAM_STOP_U | BEAM_STOP_D | BEAM_STOP_L | BEAM_STOP_R => {
+            let color = inc[(tile - BEAM_STOP_U) as usize];
+            [color, color]
+        }
+        _ => {
+            // reflectors take the color from a side they accept, orbs from any
+            let (accept, _) = reflector_arms(clipped);
+            let mut color = COLOR_INVALID;
+            for side in 0..4 {
+                if COLOR_INVALID != inc[side] && (accept[side] || !accept.contains(&true)) {
+                    color = inc[side];
+                    break;
+                }
+            }
+            [color, color]
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::levels::{self, Block};
+
+    // a bare board from its map rows, edges included
+    fn board(rows: &[&str]) -> SceneData {
+        let map: Vec<u8> = rows.iter().flat_map(|r| r.bytes()).collect();
+        let block = Block {
+            sz: rows.len(),
+            norbs: map.iter().filter(|c| levels::is_orb_cell(**c)).count(),
+            npcs: 0,
+            pieces: Vec::new(),
+            listed: false,
+            par: 0,
+            map,
+        };
+        scene::build_scene_empty(&block)
+    }
+
+    fn piece(tile: u16, idx: usize) -> PieceData {
+        PieceData { tile, board_idx: idx as u16, ..Default::default() }
+    }
+
+    #[test]
+    fn splitter_lights_both_sides() {
+        let scene = board(&[
+            ".......",
+            ".......",
+            ".......",
+            ".o...o.",
+            ".......",
+            ".......",
+            ".......",
+        ]);
+
+        // fired up the middle column into a splitter
+        let traced = trace(&scene, &[piece(REFLECTOR_U, 24)], 45);
+        assert_eq!(vec![22, 26], traced.lit_orbs);
+        assert_eq!(BEAM_H, traced.beams[23]);
+        assert_eq!(BEAM_H, traced.beams[25]);
+
+        let traced = trace(&scene, &[], 45);
+        assert!(traced.lit_orbs.is_empty());
+    }
+
+    #[test]
+    fn sources_clip_as_sources() {
+        let scene = board(&[
+            ".......",
+            ".......",
+            ".......",
+            ".......",
+            ".......",
+            ".......",
+            ".......",
+        ]);
+
+        // every edge cell but the corners is a source, both when the scene is
+        // built and when the clipping is rebuilt around the pieces
+        let clipping = scene::build_clipping(&scene, &[]);
+        for idx in sources(&scene) {
+            assert_eq!(CLIPPING_SOURCE, scene.clipping[idx]);
+            assert_eq!(CLIPPING_SOURCE, clipping[idx]);
+        }
+        assert_eq!(20, sources(&scene).len());
+
+        // fired up an empty column, the source across the board is left dark
+        let traced = trace(&scene, &[], 45);
+        for idx in [10, 17, 24, 31, 38] {
+            assert_eq!(BEAM_V, traced.beams[idx]);
+        }
+        assert_eq!(0, traced.beams[3]);
+    }
+
+    #[test]
+    fn locked_pieces_are_not_tinted() {
+        for tile in REFLECTOR_LOCKED..REFLECTOR_LOCKED + 8 {
+            assert_eq!(COLOR_WHITE, tile_color(tile));
+            assert_eq!(tile, untinted(tile));
+            assert!(!is_source(tile));
+            assert!(!is_orb(tile));
+        }
+    }
+
+    #[test]
+    fn filter_recolors_and_blocks() {
+        // white light comes out of a red filter red, enough for a red orb
+        let scene = board(&[
+            ".......",
+            ".......",
+            ".......",
+            "..R.r..",
+            ".......",
+            ".......",
+            ".......",
+        ]);
+        let traced = trace(&scene, &[], 21);
+        assert_eq!(vec![25], traced.lit_orbs);
+        assert_eq!(COLOR_RED, traced.colors[24]);
+
+        // a green filter stops red light
+        let scene = board(&[
+            ".......",
+            ".......",
+            ".......",
+            "R.G.r..",
+            ".......",
+            ".......",
+            ".......",
+        ]);
+        let traced = trace(&scene, &[], 21);
+        assert!(traced.lit_orbs.is_empty());
+        assert_eq!(0, traced.beams[24]);
+
+        // and a white beam alone doesn't light a red orb
+        let scene = board(&[
+            ".......",
+            ".......",
+            ".......",
+            "....r..",
+            ".......",
+            ".......",
+            ".......",
+        ]);
+        assert!(trace(&scene, &[], 21).lit_orbs.is_empty());
+    }
+
+    #[test]
+    fn portal_loop_comes_back_through_its_own_portal() {
+        let scene = board(&[
+            ".......",
+            "....o..",
+            "....1..",
+            "..1....",
+            ".......",
+            ".......",
+            ".......",
+        ]);
+
+        // in the left portal going right, out of the other one and around
+        // through three reflectors back into the left portal from below,
+        // which sends it up out of the other one into the orb
+        let pieces = [piece(REFLECTOR_TR, 19), piece(REFLECTOR_BR, 40), piece(REFLECTOR_BL, 37)];
+        let traced = trace(&scene, &pieces, 21);
+        assert_eq!(vec![11], traced.lit_orbs);
+        assert_eq!(BEAM_V, traced.beams[30]);
+        assert_eq!(BEAM_H, traced.beams[38]);
+        assert_eq!(BEAM_V, traced.beams[33]);
+    }
+}
@@ -1,7 +1,14 @@
 use crate::mgfw;
 
+use super::beam;
 use super::game_state;
+use super::history;
+use super::levels;
+use super::progress;
+use super::savegame;
 use super::scene;
+use super::solver;
+use super::speedrun;
 use super::ui;
 use super::enums::*;
 
@@ -9,7 +16,17 @@ pub struct GameDataHeap {
     // WARNING: Anything below this line is not in cache!
     pub scene_data: scene::SceneData,
     pub level_data: game_state::LevelData,
-    pub history: Vec<game_state::HistoryData>,
+    pub history: history::UndoTree,
+    pub beam_trace: beam::BeamTrace,
+    pub hint_job: Option<solver::HintJob>,
+    pub par_job: Option<solver::ParJob>,
+    pub speedrun: speedrun::SpeedrunData,
+    pub progress: progress::Progress,
+    pub saved_board: Option<savegame::SavedBoard>,
+    pub import: Option<String>, // shared code from --import, played instead of level 1
+    pub shared: Option<levels::Block>, // block of the shared board in play, None on a campaign or daily board
+    pub today: u32, // local date as yyyymmdd when the game started
+    pub saves: bool, // read and write the save files, not in a recorded session
 }
 
 impl Default for GameDataHeap {
@@ -17,7 +34,17 @@ impl Default for GameDataHeap {
         GameDataHeap {
             scene_data: scene::SceneData::empty(),
             level_data: game_state::LevelData::new(),
-            history: Vec::new(),
+            history: history::UndoTree::new(),
+            beam_trace: beam::BeamTrace::default(),
+            hint_job: None,
+            par_job: None,
+            speedrun: speedrun::SpeedrunData::default(),
+            progress: progress::Progress::default(),
+            saved_board: None,
+            import: None,
+            shared: None,
+            today: 0,
+            saves: true,
         }
     }
 }
@@ -42,6 +69,8 @@ pub struct GameData {
     pub inventory_ent: usize,
     pub pieces_ent: usize,
     pub holding_ent: usize,
+    pub hint_ent: usize,
+    pub cursor_ent: usize,
 
     pub pieces: [game_state::PieceData; MAX_PIECES],
 
@@ -55,6 +84,23 @@ pub struct GameData {
     pub gradient_ent: usize,
     pub transition_ent: usize,
     pub complete_ent: usize,
+    pub result_ent: usize,
+    pub speedrun_ent: usize,
+    pub daily_ent: usize,
+    pub continue_ent: usize,
+    pub select_ent: usize,
+    pub select_title_ent: usize,
+    pub select_prev_ent: usize,
+    pub select_next_ent: usize,
+    pub select_back_ent: usize,
+    pub select_ents: [usize; SELECT_GROUPS * 2],
+    pub timer_ent: usize,
+    pub split_ents: [usize; SPLIT_ROWS],
+    pub debug_ent: usize,
+    pub debug_overlay: bool,
+    pub branch_ent: usize,
+    pub branch_prev_ent: usize,
+    pub branch_next_ent: usize,
 
     pub level_complete: bool,
     pub level_ent: usize,
@@ -62,7 +108,7 @@ pub struct GameData {
     pub blackout_alpha: u8,
 
     pub final_level: bool,
-    pub history_idx: usize,
+    pub history_idx: usize, // node in heap.history the board is at
     
     pub copyright_ent: usize,
     pub version_ent: usize,
@@ -78,7 +124,7 @@ pub fn initialize(cache: &mut GameData, heap: &mut GameDataHeap, world: &mut mgf
 
     cache.level_complete = false;
     cache.final_level = false;
-    cache.history_idx = 0;
+    cache.history_idx = history::NODE_ROOT;
 
     let ent = world.new_entity();
     world.entity_set_billboard(ent, String::from("assets/background.png"));
@@ -99,10 +145,10 @@ pub fn initialize(cache: &mut GameData, heap: &mut GameDataHeap, world: &mut mgf
 
     // todo - consider moving these somewhere else
     cache.tileset_ent = world.new_entity();
-    world.entity_set_tileset(cache.tileset_ent, String::from("assets/tiles.png"), 128, 128, 16, 16);
+    world.entity_set_tileset(cache.tileset_ent, String::from("assets/tiles.png"), 128, 336, 16, 16);
 
     cache.ui_tiles_ent = world.new_entity();
-    world.entity_set_tileset(cache.ui_tiles_ent, String::from("assets/ui.png"), 64, 64, 16, 16);
+    world.entity_set_tileset(cache.ui_tiles_ent, String::from("assets/ui.png"), 64, 80, 16, 16);
 
     cache.gradient_ent = world.new_entity();
     world.entity_set_tileset(cache.gradient_ent, String::from("assets/gradient.png"), 32, 32, 4, 4);
@@ -143,6 +189,13 @@ pub fn initialize(cache: &mut GameData, heap: &mut GameDataHeap, world: &mut mgf
     cache.pieces_ent = world.new_entity();
     world.entity_set_scale_xy(cache.pieces_ent, 16.0, 16.0);
 
+    cache.hint_ent = world.new_entity();
+    world.entity_set_scale_xy(cache.hint_ent, 16.0, 16.0);
+
+    // board cell under a pointer moved by keys or a gamepad
+    cache.cursor_ent = world.new_entity();
+    world.entity_set_scale_xy(cache.cursor_ent, 16.0, 16.0);
+
     cache.level_ent = world.new_entity();
 
     game_state::initialize(cache, heap, world, version);
@@ -182,6 +235,102 @@ pub fn initialize(cache: &mut GameData, heap: &mut GameDataHeap, world: &mut mgf
     let w = world.text_get_width(cache.complete_ent) as f32;
     world.entity_set_position_xy(cache.complete_ent, SCREEN_XRES_HALF as f32 - w * 1.5, SCREEN_YRES_HALF as f32 - 8.0 * 2.0);
 
+    // moves and stars under the banner, filled in when the level is won
+    cache.result_ent = world.new_entity();
+    world.entity_set_text(cache.result_ent, String::from(" "));
+    world.entity_set_scale_xy(cache.result_ent, 1.0, 1.0);
+    world.entity_set_visibility(cache.result_ent, true);
+    world.entity_set_alpha(cache.result_ent, 0.0);
+
+    // speedrun toggle and daily puzzle under the start button, timer and splits in the corner
+    cache.speedrun_ent = world.new_entity();
+    world.entity_set_text(cache.speedrun_ent, String::from("Speedrun: off"));
+    world.entity_set_position_xy(cache.speedrun_ent, SCREEN_XRES_HALF as f32 - (world.text_get_width(cache.speedrun_ent) as f32 * 0.5).floor(), 174.0);
+    world.entity_set_color_rgba(cache.speedrun_ent, 0.5, 0.6, 0.7, 1.0);
+    world.entity_set_visibility(cache.speedrun_ent, true);
+
+    cache.daily_ent = world.new_entity();
+    world.entity_set_text(cache.daily_ent, String::from("Daily puzzle"));
+    world.entity_set_position_xy(cache.daily_ent, SCREEN_XRES_HALF as f32 - (world.text_get_width(cache.daily_ent) as f32 * 0.5).floor(), 188.0);
+    world.entity_set_color_rgba(cache.daily_ent, 0.5, 0.6, 0.7, 1.0);
+    world.entity_set_visibility(cache.daily_ent, true);
+
+    // only offered when there is a saved campaign board to go back to
+    cache.continue_ent = world.new_entity();
+    let text = game_state::continue_text(heap);
+    world.entity_set_text(cache.continue_ent, text.clone().unwrap_or(String::from(" ")));
+    world.entity_set_position_xy(cache.continue_ent, SCREEN_XRES_HALF as f32 - (world.text_get_width(cache.continue_ent) as f32 * 0.5).floor(), 202.0);
+    world.entity_set_color_rgba(cache.continue_ent, 0.5, 0.6, 0.7, 1.0);
+    world.entity_set_visibility(cache.continue_ent, text.is_some());
+
+    cache.select_ent = world.new_entity();
+    world.entity_set_text(cache.select_ent, String::from("Select level"));
+    world.entity_set_position_xy(cache.select_ent, SCREEN_XRES_HALF as f32 - (world.text_get_width(cache.select_ent) as f32 * 0.5).floor(), 216.0);
+    world.entity_set_color_rgba(cache.select_ent, 0.5, 0.6, 0.7, 1.0);
+    world.entity_set_visibility(cache.select_ent, true);
+
+    // level select page, one entry per sub-level, filled in when the page is shown
+    cache.select_title_ent = world.new_entity();
+    world.entity_set_text(cache.select_title_ent, String::from(" "));
+    world.entity_set_visibility(cache.select_title_ent, false);
+
+    cache.select_prev_ent = world.new_entity();
+    world.entity_set_text(cache.select_prev_ent, String::from("< Prev"));
+    world.entity_set_position_xy(cache.select_prev_ent, 64.0, 184.0);
+    world.entity_set_visibility(cache.select_prev_ent, false);
+
+    cache.select_next_ent = world.new_entity();
+    world.entity_set_text(cache.select_next_ent, String::from("Next >"));
+    world.entity_set_position_xy(cache.select_next_ent, SCREEN_XRES as f32 - 64.0 - world.text_get_width(cache.select_next_ent) as f32, 184.0);
+    world.entity_set_visibility(cache.select_next_ent, false);
+
+    cache.select_back_ent = world.new_entity();
+    world.entity_set_text(cache.select_back_ent, String::from("Back"));
+    world.entity_set_position_xy(cache.select_back_ent, SCREEN_XRES_HALF as f32 - (world.text_get_width(cache.select_back_ent) as f32 * 0.5).floor(), 204.0);
+    world.entity_set_visibility(cache.select_back_ent, false);
+
+    for i in 0..SELECT_GROUPS * 2 {
+        let col = i / 2 / (SELECT_GROUPS / 2);
+        let row = i / 2 % (SELECT_GROUPS / 2);
+        cache.select_ents[i] = world.new_entity();
+        world.entity_set_text(cache.select_ents[i], String::from(" "));
+        world.entity_set_position_xy(cache.select_ents[i], (64 + col * 148 + (i % 2) * 64) as f32, (44 + row * 16) as f32);
+        world.entity_set_visibility(cache.select_ents[i], false);
+    }
+
+    cache.timer_ent = world.new_entity();
+    world.entity_set_text(cache.timer_ent, speedrun::format_time(0));
+    world.entity_set_position_xy(cache.timer_ent, 4.0, 4.0);
+    world.entity_set_visibility(cache.timer_ent, false);
+
+    for i in 0..SPLIT_ROWS {
+        cache.split_ents[i] = world.new_entity();
+        world.entity_set_text(cache.split_ents[i], String::from(" "));
+        world.entity_set_position_xy(cache.split_ents[i], 4.0, 18.0 + 10.0 * i as f32);
+        world.entity_set_visibility(cache.split_ents[i], false);
+    }
+
+    // seed and level option of the current board, toggled with f3
+    cache.debug_ent = world.new_entity();
+    world.entity_set_text(cache.debug_ent, String::from(" "));
+    world.entity_set_color_rgba(cache.debug_ent, 0.7, 0.7, 0.7, 1.0);
+    world.entity_set_visibility(cache.debug_ent, false);
+    cache.debug_overlay = false;
+
+    // undo tree branches under the inventory, only shown once there is more than one
+    cache.branch_ent = world.new_entity();
+    world.entity_set_text(cache.branch_ent, String::from(" "));
+    world.entity_set_color_rgba(cache.branch_ent, 0.5, 0.6, 0.7, 1.0);
+    world.entity_set_visibility(cache.branch_ent, false);
+
+    cache.branch_prev_ent = world.new_entity();
+    world.entity_set_text(cache.branch_prev_ent, String::from("<"));
+    world.entity_set_visibility(cache.branch_prev_ent, false);
+
+    cache.branch_next_ent = world.new_entity();
+    world.entity_set_text(cache.branch_next_ent, String::from(">"));
+    world.entity_set_visibility(cache.branch_next_ent, false);
+
 
 
 }
@@ -192,7 +341,11 @@ pub fn quit_requested(cache: &mut GameData) -> bool {
     cache.quit_requested
 }
 
-pub fn shutdown(_cache: &mut GameData, heap: &mut GameDataHeap) {
+pub fn shutdown(cache: &mut GameData, heap: &mut GameDataHeap) {
+    // keep an unfinished board for next time
+    ui::let_go(cache);
+    game_state::save_board(cache, heap);
+
     // deallocate and overwrite existing memory
     *heap = GameDataHeap::default();
 
@@ -203,21 +356,28 @@ pub fn shutdown(_cache: &mut GameData, heap: &mut GameDataHeap) {
 
 // this gets called by MGFW with input events
 #[rustfmt::skip]
-pub fn event(cache: &mut GameData, heap: &mut GameDataHeap, world: &mut mgfw::ecs::World, event_id: u8) -> bool {
+pub fn event(cache: &mut GameData, heap: &mut GameDataHeap, world: &mut mgfw::ecs::World, _event_id: u8) -> bool {
     let mut consumed = false;
 
-    if mgfw::EVENT_INPUT_KEYBOARD_RELEASED_ESCAPE == event_id {
+    if world.input.released(ACTION_QUIT) {
         cache.quit_requested = true;
         consumed = true;
-    
-    } else if mgfw::EVENT_INPUT_MOUSE_BUTTON_UP == event_id {
-        consumed = ui::click(cache, heap, world);
+    }
+
+    if world.input.pressed(ACTION_DEBUG) {
+        cache.debug_overlay = !cache.debug_overlay;
+        consumed = true;
+    }
 
-    } else if mgfw::EVENT_INPUT_MOUSE_BUTTON_DOWN == event_id {
-        consumed = ui::click_down(cache, heap, world);
-        
+    if world.input.pressed(ACTION_EXPORT) || world.input.pressed(ACTION_EXPORT_SOLUTION) {
+        consumed |= ui::export_board(cache, heap, world.input.pressed(ACTION_EXPORT_SOLUTION));
     }
 
+    if world.input.pressed(ACTION_IMPORT) {
+        consumed |= ui::import_board(cache, heap, world);
+    }
+
+    consumed |= ui::actions(cache, heap, world);
     consumed
 }
 
@@ -237,6 +397,8 @@ pub fn update(cache: &mut GameData, heap: &mut GameDataHeap, world: &mut mgfw::e
         return false;
     }
 
+    speedrun::tick(&mut heap.speedrun);
+
     // update game subsystems
     expect_blown |= ui::update(cache, heap, world);
     expect_blown
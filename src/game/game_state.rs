@@ -1,35 +1,24 @@
 use crate::game::scene::update_clipping;
 use crate::mgfw;
 
+use super::beam;
 use super::game;
 use super::game::GameData;
 use super::game::GameDataHeap;
+use super::history::{self, Command};
+use super::progress;
+use super::savegame;
 use super::scene;
+use super::share;
+use super::solver;
 use super::enums::*;
+use super::levels::{self, Block};
 
-use std::fs::{self, File};
-use std::io::{self, BufRead};
+use chrono::Datelike;
+use rand_chacha::ChaCha8Rng;
+use rand::prelude::*;
 
 // Heap Data
-#[derive(Clone)]
-pub struct Block {
-    pub sz: usize,
-    pub norbs: usize,
-    pub npcs: usize,
-    pub map: Vec<u8>,
-}
-
-impl Block {
-    pub fn empty() -> Block {
-        Block {
-            sz: 0,
-            norbs: 0,
-            npcs: 0,
-            map: Vec::new(),
-        }
-    }
-}
-
 pub struct LevelData {
     options: Vec<Vec<Block>>,
 }
@@ -42,39 +31,38 @@ impl LevelData {
     }
 }
 
-pub struct HistoryData {
-    pieces: [u8; MAX_PIECES], // piece location
-    tilemap: [u16; MAP_SZ],
-}
-
-impl HistoryData {
-    pub fn new() -> HistoryData {
-        HistoryData {
-            pieces: [BOARD_IDX_INVALID; MAX_PIECES],
-            tilemap: [0; MAP_SZ],
-        }
-    }
-}
-
 // Cache Data
 pub struct PlayerData {
     level: usize,
     sub_level: usize,
     level_option: usize,
     pub level_displayed: usize,
+    pub hints: usize, // hints taken on this level
+    pub moves: usize, // placements, swaps, pickups and beam fires this attempt
+    pub par: usize, // 0 until the level's par is known
+    pub daily: u32, // yyyymmdd of the daily puzzle being played, 0 otherwise
+    pub seed: u64, // world seed the current board was built from
+    next_seed: u64,
+    jump_level: usize, // first level from --level or continue, 0 to start at the beginning
+    jump_sub_level: usize,
+    jump_option: usize,
+    restore: bool, // continue the saved board rather than starting a new one
+    pub orient: u8, // board orientation against the level's map, mirrored if 4 <= orient, then turned orient % 4 times
 }
 
+#[derive(Default, Clone, Copy)]
 pub struct PieceData {
     pub tile: u16,
-    pub board_idx: u8,
+    pub board_idx: u16,
     pub active: bool,
     pub home: u8,
+    pub locked: bool, // fixed on the board by the level
 }
 
 
 pub fn initialize(cache: &mut game::GameData, heap: &mut game::GameDataHeap, world: &mut mgfw::ecs::World, version: &str) {
 
-    let pcs = vec![17, 18, 23, 22, 19, 20, 24, 21, 17, 18, 23, 22, 19, 20, 24, 21, 17, 18, 23, 22, 19, 20, 24, 21];
+    let pcs = levels::standard_pieces(MAX_PIECES);
 
     let data = &mut cache.pieces;
     for i in 0..MAX_PIECES {
@@ -82,28 +70,15 @@ pub fn initialize(cache: &mut game::GameData, heap: &mut game::GameDataHeap, wor
         data[i].board_idx = BOARD_IDX_INVALID;
         data[i].home = i as u8;
         data[i].active = false;
+        data[i].locked = false;
     }
 
     // load levels
     println!("Loading maps...");
-    let file = File::open("assets/levels.dat").unwrap();
-
-    let mut blocks: Vec<Block> = Vec::new();
-
-    let reader = io::BufReader::new(file);
-    for line in reader.lines() {
-        let line = line.unwrap();
-        if line.len() < 2 { continue; }
-
-        // is this the start of a block?
-        let split: Vec<&str> = line.split(',').collect();
-        let mut block = Block::empty();
-        block.npcs = split[0].parse::<usize>().unwrap();
-        block.sz = split[1].parse::<usize>().unwrap();
-        block.norbs = split[2].parse::<usize>().unwrap();
-        block.map = expand(decompress(split[3].to_string()));
-        blocks.push(block);
-    }
+    let blocks = match levels::load_blocks("assets/levels.dat") {
+        Ok(blocks) => blocks,
+        Err(err) => panic!("assets/levels.dat: {err}"),
+    };
 
     let nblocks = blocks.len();
 
@@ -146,48 +121,283 @@ pub fn initialize(cache: &mut game::GameData, heap: &mut game::GameDataHeap, wor
     data.level = 0;
     data.sub_level = 0;
     data.level_displayed = 0;
+    data.hints = 0;
+    data.moves = 0;
+    data.par = 0;
+    data.daily = 0;
+
+    // a recorded session starts from nothing saved, and the date is pinned so
+    // the daily puzzle is the one the recording played
+    heap.saves = !world.is_recorded_session();
+    heap.speedrun.keep_pbs = heap.saves;
+    heap.today = world.pin("today", || daily_date().to_string()).parse().unwrap_or(0);
+
+    heap.progress = progress::Progress::default();
+    heap.saved_board = None;
+    if heap.saves {
+        heap.progress = progress::load_progress(progress::PROGRESS_FILE);
+        heap.saved_board = savegame::load_board(savegame::BOARD_FILE);
+    }
 
+    // yesterday's daily puzzle is gone
+    if let Some(saved) = &heap.saved_board {
+        if 0 != saved.daily && heap.today != saved.daily {
+            heap.saved_board = None;
+        }
+    }
+
+    launch_options(cache, heap, world);
+    cache.player_data.next_seed = world.seed();
+}
+
+
+// --seed <n> seeds the world generator, --level <level>.<option> starts on that
+// level option. together they rebuild the board shown in the debug overlay.
+// --import <file> starts on the shared board in the file
+fn launch_options(cache: &mut game::GameData, heap: &mut game::GameDataHeap, world: &mut mgfw::ecs::World) {
+
+    let args: Vec<String> = std::env::args().collect();
+    for i in 1..args.len() {
+        let value = match args.get(i + 1) {
+            Some(value) => value,
+            None => break,
+        };
+        if "--seed" == args[i] {
+            match value.parse::<u64>() {
+                Ok(seed) => world.set_seed(seed),
+                Err(_) => println!("--seed expects a number, got {value}"),
+            }
+        } else if "--level" == args[i] {
+            let mut split = value.split('.');
+            let level = split.next().and_then(|v| v.parse::<usize>().ok());
+            let option = split.next().map_or(Some(0), |v| v.parse::<usize>().ok());
+            match (level, option) {
+                (Some(level), Some(option)) => {
+                    cache.player_data.jump_level = level;
+                    cache.player_data.jump_sub_level = 0;
+                    cache.player_data.jump_option = option;
+                },
+                _ => println!("--level expects <level>.<option>, got {value}"),
+            }
+        } else if "--import" == args[i] {
+            match read_share(world, value) {
+                Ok(code) => heap.import = Some(code),
+                Err(err) => println!("--import {value}: {err}"),
+            }
+        }
+    }
+}
+
+
+// the launch options that rebuild the current board
+pub fn debug_text(cache: &game::GameData, world: &mgfw::ecs::World) -> String {
+    match cache.player_data.level {
+        0 => format!("--seed {}", world.seed()),
+        level => format!("--seed {} --level {}.{}", cache.player_data.seed, level, cache.player_data.level_option),
+    }
+}
+
+
+// every board restarts the world generator from its own seed, so the block
+// scatter and starting flips follow from the seed and the level option alone
+fn reseed(cache: &mut game::GameData, world: &mut mgfw::ecs::World) {
+    let seed = cache.player_data.next_seed;
+    world.set_seed(seed);
+    cache.player_data.seed = seed;
 }
 
 pub fn next_level(cache: &mut game::GameData, heap: &mut game::GameDataHeap, world: &mut mgfw::ecs::World) {
 
+    if cache.player_data.restore {
+        cache.player_data.restore = false;
+        if let Some(saved) = heap.saved_board.take() {
+            if restore_board(cache, heap, world, saved) { return; }
+        }
+        continue_level(cache, heap);
+    }
+
     if 0 == cache.player_data.level {
+        if let Some(code) = heap.import.take() {
+            match import_board(cache, heap, world, &code) {
+                Ok(()) => return,
+                Err(err) => println!("--import: {err}"),
+            }
+        }
+    }
+
+    if 0 == cache.player_data.level && 0 != cache.player_data.jump_level {
+        let nlevels = heap.level_data.options.len();
+        cache.player_data.level = cache.player_data.jump_level.min(nlevels);
+        cache.player_data.sub_level = cache.player_data.jump_sub_level % num_sub_levels(heap, cache.player_data.level);
+        cache.player_data.level_option = cache.player_data.jump_option % heap.level_data.options[cache.player_data.level-1].len();
+        cache.player_data.jump_level = 0;
+
+    } else if 0 == cache.player_data.level {
         cache.player_data.level = 1;
         cache.player_data.sub_level = 0;
         cache.player_data.level_option = 0;
 
     } else {
-        if 1 < cache.player_data.level {
-            cache.player_data.sub_level = (cache.player_data.sub_level + 1) % 2;
-        }
+        cache.player_data.sub_level = (cache.player_data.sub_level + 1) % num_sub_levels(heap, cache.player_data.level);
         if 0 == cache.player_data.sub_level {
             cache.player_data.level += 1;
-            cache.player_data.level_option = world.rnd_range(0..heap.level_data.options[cache.player_data.level-1].len());
+            cache.player_data.level_option = world.rng().gen_range(0..heap.level_data.options[cache.player_data.level-1].len());
         } else {
             cache.player_data.level_option = (cache.player_data.level_option + 1) % heap.level_data.options[cache.player_data.level-1].len();
         }
     }
 
-    println!("next level: {}, sub-level: {}, option: {}", cache.player_data.level, cache.player_data.sub_level, cache.player_data.level_option);
+    println!("next level: {}, sub-level: {}, option: {}, seed: {}", cache.player_data.level, cache.player_data.sub_level, cache.player_data.level_option, cache.player_data.seed);
 
-    if cache.player_data.level == heap.level_data.options.len() && cache.player_data.sub_level == (heap.level_data.options[cache.player_data.level-1].len() - 1) % 2 {
+    check_final_level(cache, heap);
+
+    start_level(cache, heap, world);
+}
+
+
+fn check_final_level(cache: &mut game::GameData, heap: &game::GameDataHeap) {
+    if cache.player_data.level == heap.level_data.options.len() && cache.player_data.sub_level + 1 == num_sub_levels(heap, cache.player_data.level) {
         println!("Final Level!");
         cache.final_level = true;
     }
-    
-    heap.scene_data = scene::build_scene(&heap.level_data.options[cache.player_data.level-1][cache.player_data.level_option]);
+}
 
-    // randomly flip and rotate starting layout
-    if world.rnd() < 0.5 { flip_h(cache, heap, world); }
-    if world.rnd() < 0.5 { flip_v(cache, heap, world); }
-    if world.rnd() < 0.5 { rotate(cache, heap, world); }
-    if world.rnd() < 0.5 { rotate(cache, heap, world); }
-    if world.rnd() < 0.5 { rotate(cache, heap, world); }
 
-    update_clipping(cache, heap);
+// the day's puzzle. level, option, block scatter and starting flips all come
+// from the local date, so everyone playing on the same day gets the same board.
+// the generators are named ones, StdRng may change between rand releases
+pub fn daily_level(cache: &mut game::GameData, heap: &mut game::GameDataHeap, world: &mut mgfw::ecs::World) {
 
-    world.entity_set_tilemap(cache.tilemap_ent, cache.tileset_ent, heap.scene_data.sz, &heap.scene_data.tilemap);
+    let date = heap.today;
+    let mut rng = ChaCha8Rng::seed_from_u64(date as u64);
+
+    cache.player_data.daily = date;
+    cache.player_data.level = rng.gen_range(1..=heap.level_data.options.len());
+    cache.player_data.sub_level = 0;
+    cache.player_data.level_option = rng.gen_range(0..heap.level_data.options[cache.player_data.level-1].len());
+    cache.player_data.next_seed = date as u64;
+
+    println!("daily puzzle {date}: level {}, option {}", cache.player_data.level, cache.player_data.level_option);
+
+    // a single board, there is no next level after it
+    cache.final_level = true;
+
+    start_level(cache, heap, world);
+}
+
+
+pub fn num_levels(heap: &game::GameDataHeap) -> usize {
+    heap.level_data.options.len()
+}
+
+
+// boards played on a level, next_level and the level select both go by it. level 1
+// has one and the rest two, but the final level ends on its first board when it has
+// an odd number of options
+pub fn num_sub_levels(heap: &game::GameDataHeap, level: usize) -> usize {
+    let options = heap.level_data.options[level-1].len();
+    if 1 == level || (level == heap.level_data.options.len() && 1 == options % 2) { 1 } else { 2 }
+}
+
+
+// the first level, anything already won, the one after it and the saved board
+pub fn level_unlocked(heap: &game::GameDataHeap, level: usize, sub_level: usize) -> bool {
+    let d = level_displayed(level, sub_level);
+    1 == d ||
+        progress::is_completed(&heap.progress, d) ||
+        progress::is_completed(&heap.progress, d - 1) ||
+        d == level_displayed(heap.progress.level, heap.progress.sub_level)
+}
+
+
+// start on the picked level and sub-level with any of its options, see next_level
+pub fn select_level(cache: &mut game::GameData, heap: &mut game::GameDataHeap, world: &mut mgfw::ecs::World, level: usize, sub_level: usize) {
+    cache.player_data.jump_level = level;
+    cache.player_data.jump_sub_level = sub_level;
+    cache.player_data.jump_option = world.rng().gen_range(0..heap.level_data.options[level-1].len());
+}
+
+
+// main menu entry for continue, None when there is nothing to continue
+pub fn continue_text(heap: &game::GameDataHeap) -> Option<String> {
+    let won = progress::num_completed(&heap.progress);
+    match &heap.saved_board {
+        Some(saved) if 0 != saved.daily => Some(String::from("Continue: daily puzzle")),
+        Some(saved) => Some(format!("Continue: level {} ({} won)", level_displayed(saved.level, saved.sub_level), won)),
+        None if 0 != heap.progress.level => Some(format!("Continue: level {} ({} won)", level_displayed(heap.progress.level, heap.progress.sub_level), won)),
+        None => None,
+    }
+}
+
+
+// pick up the saved board as it was left, or else the campaign at the saved
+// level, see next_level
+pub fn continue_level(cache: &mut game::GameData, heap: &mut game::GameDataHeap) {
+    if heap.saved_board.is_some() {
+        cache.player_data.restore = true;
+        return;
+    }
+    cache.player_data.jump_level = heap.progress.level;
+    cache.player_data.jump_sub_level = heap.progress.sub_level;
+    cache.player_data.jump_option = heap.progress.level_option;
+}
+
+
+// the campaign level won, the daily puzzle and shared boards are not part of it
+pub fn complete_level(cache: &mut game::GameData, heap: &mut game::GameDataHeap) {
+
+    println!("move log: {}", move_log(cache, heap));
+
+    finish_par(cache, heap);
+
+    if 0 != cache.player_data.daily || heap.shared.is_some() { return; }
+
+    let n = stars(cache.player_data.moves, cache.player_data.par);
+    progress::complete(&mut heap.progress, cache.player_data.level_displayed, n, cache.final_level);
+    save_progress(heap);
+}
+
+
+fn save_progress(heap: &game::GameDataHeap) {
+    if !heap.saves { return; }
+    if let Err(err) = progress::save_progress(progress::PROGRESS_FILE, &heap.progress) {
+        println!("{}: {err}, progress not saved", progress::PROGRESS_FILE);
+    }
+}
+
+
+// level 1 has a single board, every later level has two
+pub fn level_displayed(level: usize, sub_level: usize) -> usize {
+    match level {
+        0 | 1 => level,
+        _ => (level - 1) * 2 + sub_level,
+    }
+}
+
+
+// local date as yyyymmdd, see GameDataHeap::today
+fn daily_date() -> u32 {
+    let today = chrono::Local::now().date_naive();
+    today.year() as u32 * 10000 + today.month() * 100 + today.day()
+}
+
+
+fn start_level(cache: &mut game::GameData, heap: &mut game::GameDataHeap, world: &mut mgfw::ecs::World) {
+    
+    build_board(cache, heap, world);
+    enter_board(cache, heap, world);
+
+    cache.player_data.hints = 0;
+    start_attempt(cache, heap);
     
+    clear_history(cache, heap);
+
+}
+
+
+// show the game screen for the board just laid out
+fn enter_board(cache: &mut game::GameData, heap: &mut game::GameDataHeap, world: &mut mgfw::ecs::World) {
+
     world.entity_set_visibility(cache.source_ent, false);    
     world.entity_set_visibility(cache.tilemap_ent, true);
     world.entity_set_visibility(cache.reflector_ent, true);
@@ -200,65 +410,382 @@ pub fn next_level(cache: &mut game::GameData, heap: &mut game::GameDataHeap, wor
     world.entity_set_visibility(cache.level_ent, true);
     world.entity_set_visibility(cache.logo_mini_ent, true);
 
+    cache.level_complete = false;
+    cache.player_data.level_displayed = match heap.shared {
+        Some(_) => 0,
+        None => level_displayed(cache.player_data.level, cache.player_data.sub_level),
+    };
+
+    if 0 == cache.player_data.daily && heap.shared.is_none() {
+        progress::resume_at(&mut heap.progress, cache.player_data.level, cache.player_data.sub_level, cache.player_data.level_option);
+        save_progress(heap);
+    }
+}
+
+
+// lay out the chosen level option. the world generator is reseeded first and
+// decides the block scatter and the starting flips and rotations
+fn build_board(cache: &mut game::GameData, heap: &mut game::GameDataHeap, world: &mut mgfw::ecs::World) {
+
+    reseed(cache, world);
+    heap.shared = None;
+
+    heap.scene_data = scene::build_scene(&heap.level_data.options[cache.player_data.level-1][cache.player_data.level_option], world.rng());
+
+    let npcs = heap.scene_data.num_pcs as usize;
+    scene::lock_pieces(&heap.level_data.options[cache.player_data.level-1][cache.player_data.level_option], &mut cache.pieces[0..npcs]);
+
+    // randomly flip and rotate starting layout
+    cache.player_data.orient = 0;
+    if world.rng().gen::<f32>() < 0.5 { flip_h(cache, heap, world); }
+    if world.rng().gen::<f32>() < 0.5 { flip_v(cache, heap, world); }
+    if world.rng().gen::<f32>() < 0.5 { rotate(cache, heap, world); }
+    if world.rng().gen::<f32>() < 0.5 { rotate(cache, heap, world); }
+    if world.rng().gen::<f32>() < 0.5 { rotate(cache, heap, world); }
+    cache.player_data.next_seed = world.rng().gen();
+
+    show_board(cache, heap, world);
+}
+
+
+fn show_board(cache: &mut game::GameData, heap: &mut game::GameDataHeap, world: &mut mgfw::ecs::World) {
+
+    let npcs = heap.scene_data.num_pcs as usize;
+
+    update_clipping(cache, heap);
+
+    world.entity_set_tilemap(cache.tilemap_ent, cache.tileset_ent, heap.scene_data.sz, &heap.scene_data.tilemap);
+
     // update inventory geometry
-    let inv = vec![GRID; heap.scene_data.num_pcs as usize];
+    let mut inv = vec![0; npcs.div_ceil(4) * 4];
+    for i in 0..npcs {
+        inv[i] = GRID;
+    }
     world.entity_set_tilemap(cache.inventory_ent, cache.tileset_ent, 4, &inv);
 
-    let pcs = match heap.scene_data.num_pcs {
-        8 => vec![17, 18, 23, 22, 19, 20, 24, 21],
-        16 => vec![17, 18, 23, 22, 19, 20, 24, 21, 17, 18, 23, 22, 19, 20, 24, 21],
-        24 => vec![17, 18, 23, 22, 19, 20, 24, 21, 17, 18, 23, 22, 19, 20, 24, 21, 17, 18, 23, 22, 19, 20, 24, 21],
-        _ => vec![],
-    };
+    let pcs = scene::inventory_tiles(&cache.pieces[0..npcs]);
     world.entity_set_tilemap(cache.pieces_ent, cache.tileset_ent, 4, &pcs);
+}
 
-    cache.level_complete = false;
-    if 1 == cache.player_data.level {
-        cache.player_data.level_displayed = 1;
-    } else {
-        cache.player_data.level_displayed = (cache.player_data.level - 1) * 2 + cache.player_data.sub_level;
+
+// the unfinished board goes to the save file on quit, a won one clears it.
+// nothing changes if the game never left the main menu, is on a shared board
+// or is a recorded session
+pub fn save_board(cache: &mut game::GameData, heap: &game::GameDataHeap) {
+
+    if 0 == cache.player_data.level || heap.shared.is_some() || !heap.saves { return; }
+
+    if cache.level_complete {
+        savegame::delete_board(savegame::BOARD_FILE);
+        return;
     }
-    
+
+    let npcs = heap.scene_data.num_pcs as usize;
+    let data = savegame::SavedBoard {
+        level: cache.player_data.level,
+        sub_level: cache.player_data.sub_level,
+        level_option: cache.player_data.level_option,
+        seed: cache.player_data.seed,
+        daily: cache.player_data.daily,
+        final_level: cache.final_level,
+        moves: cache.player_data.moves,
+        hints: cache.player_data.hints,
+        orient: cache.player_data.orient,
+        tilemap: heap.scene_data.tilemap.clone(),
+        pieces: cache.pieces[0..npcs].to_vec(),
+        history_idx: cache.history_idx,
+        ..Default::default()
+    };
+
+    if let Err(err) = savegame::save_board(savegame::BOARD_FILE, &data, &heap.history) {
+        println!("{}: {err}, board not saved", savegame::BOARD_FILE);
+    }
+}
+
+
+// a saved board holds the level's pieces and layout turned to its orientation,
+// with blocks scattered on the floor
+fn fits_level(block: &Block, saved: &savegame::SavedBoard) -> bool {
+
+    let mut want: Vec<u16> = block.pieces.iter().map(|t| oriented_piece(*t, saved.orient)).collect();
+    let mut have: Vec<u16> = saved.pieces.iter().map(|p| p.tile).collect();
+    want.sort();
+    have.sort();
+    if want != have { return false; }
+
+    let layout = Block { map: scene::oriented_map(block, saved.orient), ..block.clone() };
+    let expected = scene::build_scene_empty(&layout).tilemap;
+    expected.iter().zip(saved.tilemap.iter()).all(|(e, t)| e == t || (scene::is_floor(*e) && (scene::is_floor(*t) || BLOCK == *t)))
+}
+
+
+// put a saved board back as it was left, false if it no longer fits the levels
+fn restore_board(cache: &mut game::GameData, heap: &mut game::GameDataHeap, world: &mut mgfw::ecs::World, saved: savegame::SavedBoard) -> bool {
+
+    if 0 == saved.level || saved.level > heap.level_data.options.len() { return false; }
+    if saved.level_option >= heap.level_data.options[saved.level-1].len() { return false; }
+
+    let block = &heap.level_data.options[saved.level-1][saved.level_option];
+    let sz2 = block.sz * block.sz;
+    if sz2 != saved.tilemap.len() || block.npcs != saved.pieces.len() { return false; }
+    if saved.pieces.iter().any(|p| BOARD_IDX_INVALID != p.board_idx && p.board_idx as usize >= sz2) { return false; }
+    if saved.history.nodes.iter().any(|n| !history::fits(&n.cmd, block.npcs, sz2)) { return false; }
+    if !fits_level(block, &saved) { return false; }
+
+    heap.scene_data = scene::build_scene_empty(block);
+    heap.scene_data.tilemap = saved.tilemap;
+    heap.shared = None;
+    scene::link_portals(&mut heap.scene_data);
+
+    let npcs = saved.pieces.len();
+    cache.pieces[0..npcs].copy_from_slice(&saved.pieces);
+
+    cache.player_data.level = saved.level;
+    cache.player_data.sub_level = saved.sub_level;
+    cache.player_data.level_option = saved.level_option;
+    cache.player_data.seed = saved.seed;
+    cache.player_data.daily = saved.daily;
+    cache.player_data.orient = saved.orient;
+    cache.final_level = saved.final_level;
+
+    println!("restored level: {}, sub-level: {}, option: {}, seed: {}", saved.level, saved.sub_level, saved.level_option, saved.seed);
+
+    show_board(cache, heap, world);
+    enter_board(cache, heap, world);
+
+    start_attempt(cache, heap);
+    cache.player_data.moves = saved.moves;
+    cache.player_data.hints = saved.hints;
+
+    heap.history = saved.history;
+    cache.history_idx = saved.history_idx;
+
+    true
+}
+
+
+// the current board as a shareable code, see share::encode
+pub fn export_board(cache: &game::GameData, heap: &game::GameDataHeap, with_placements: bool) -> String {
+    let npcs = heap.scene_data.num_pcs as usize;
+    let block = current_block(cache, heap);
+    share::encode(block, cache.player_data.orient, &heap.scene_data.tilemap, &cache.pieces[0..npcs], with_placements)
+}
+
+
+// the code in a share file, pinned so a replay plays the board the recording read
+pub fn read_share(world: &mut mgfw::ecs::World, filename: &str) -> Result<String, String> {
+
+    let mut err = None;
+    let code = world.pin("share", || match std::fs::read_to_string(filename) {
+        Ok(code) => String::from(code.trim()),
+        Err(e) => {
+            err = Some(e.to_string());
+            String::new()
+        },
+    });
+
+    match code.is_empty() {
+        true => Err(err.unwrap_or_else(|| String::from("no share code"))),
+        false => Ok(code),
+    }
+}
+
+
+// lay out the board a shared code describes. it is played on its own like the
+// daily puzzle, the campaign levels are left as they are
+pub fn import_board(cache: &mut game::GameData, heap: &mut game::GameDataHeap, world: &mut mgfw::ecs::World, code: &str) -> Result<(), String> {
+
+    let shared = share::decode(code)?;
+
+    cache.player_data.daily = 0;
+    cache.final_level = true;
+
+    println!("imported board: {} pieces, size {}, {} orbs", shared.block.npcs, shared.block.sz, shared.block.norbs);
+
+    heap.scene_data = scene::build_scene_empty(&shared.block);
+
+    let npcs = heap.scene_data.num_pcs as usize;
+    scene::lock_pieces(&shared.block, &mut cache.pieces[0..npcs]);
+    heap.shared = Some(shared.block);
+
+    // turn the level into the shared orientation, then scatter and place in it
+    cache.player_data.orient = 0;
+    if 4 <= shared.orient { flip_h(cache, heap, world); }
+    for _ in 0..shared.orient % 4 {
+        rotate(cache, heap, world);
+    }
+
+    for i in 0..shared.blocked.len() {
+        let tile = heap.scene_data.tilemap[i];
+        if shared.blocked[i] && scene::is_floor(tile) {
+            heap.scene_data.tilemap[i] = BLOCK;
+        }
+    }
+
+    for (idx, tile) in shared.placements {
+        let free = !cache.pieces[0..npcs].iter().any(|p| idx as u16 == p.board_idx);
+        let tilemap = heap.scene_data.tilemap[idx];
+        if !free || !scene::is_floor(tilemap) { continue; }
+        if let Some(p) = cache.pieces[0..npcs].iter_mut().find(|p| tile == p.tile && BOARD_IDX_INVALID == p.board_idx) {
+            p.board_idx = idx as u16;
+        }
+    }
+
+    show_board(cache, heap, world);
+    enter_board(cache, heap, world);
+
+    cache.player_data.hints = 0;
+    start_attempt(cache, heap);
+
     clear_history(cache, heap);
-    push_history(cache, heap);
 
+    Ok(())
 }
 
 
 pub fn reset_level(cache: &mut GameData, heap: &mut GameDataHeap) {
 
     let mut update_history = false;
-    for i in 0..heap.scene_data.num_pcs as usize {
-        if BOARD_IDX_INVALID != cache.pieces[i].board_idx {
+    let pieces: Box<[u16]> = cache.pieces[0..heap.scene_data.num_pcs as usize].iter().map(|p| p.board_idx).collect();
+    for p in cache.pieces[0..heap.scene_data.num_pcs as usize].iter() {
+        if BOARD_IDX_INVALID != p.board_idx && !p.locked {
             update_history = true;
-            break;
         }
     }
 
     scene::reset_scene(cache, heap);
 
     if update_history {
-        push_history(cache, heap);
+        push_history(cache, heap, Command::Reset { pieces });
     }
 }
 
 
 pub fn trash_level(cache: &mut game::GameData, heap: &mut game::GameDataHeap, world: &mut mgfw::ecs::World) {
+
+    // everyone gets the same daily board, no rerolling it or a shared one
+    if 0 != cache.player_data.daily || heap.shared.is_some() { return; }
     
-    cache.player_data.level_option = world.rnd_range(0..heap.level_data.options[cache.player_data.level-1].len());
-    
-    heap.scene_data = scene::build_scene(&heap.level_data.options[cache.player_data.level-1][cache.player_data.level_option]);
-    
-    world.entity_set_tilemap(cache.tilemap_ent, cache.tileset_ent, heap.scene_data.sz, &heap.scene_data.tilemap);
+    cache.player_data.level_option = world.rng().gen_range(0..heap.level_data.options[cache.player_data.level-1].len());
     
-    scene::reset_scene(cache, heap);
+    build_board(cache, heap, world);
+    start_attempt(cache, heap);
 
     clear_history(cache, heap);
-    push_history(cache, heap);
+}
+
+
+// fresh move count, and look up the par in the background, see poll_par
+fn start_attempt(cache: &mut GameData, heap: &mut GameDataHeap) {
+
+    cache.player_data.moves = 0;
+    cache.player_data.par = 0;
+
+    cancel_par(heap);
+    let job = solver::ParJob::spawn(current_block(cache, heap));
+    heap.par_job = Some(job);
+}
+
+
+// the block the board in play was laid out from
+fn current_block<'a>(cache: &GameData, heap: &'a GameDataHeap) -> &'a Block {
+    match &heap.shared {
+        Some(block) => block,
+        None => &heap.level_data.options[cache.player_data.level-1][cache.player_data.level_option],
+    }
+}
+
+
+pub fn poll_par(cache: &mut GameData, heap: &mut GameDataHeap) {
+
+    match &heap.par_job {
+        Some(job) if job.is_finished() => (),
+        _ => return,
+    }
+
+    if let Some(par) = heap.par_job.take().and_then(|job| job.join()) {
+        cache.player_data.par = par;
+    }
+}
+
+
+// the par to rate a win against, without waiting on a search still running.
+// when the solver hasn't settled it the search is dropped for the level's own
+// par, or failing that every piece placed plus the move firing the beam
+fn finish_par(cache: &mut GameData, heap: &mut GameDataHeap) {
+
+    poll_par(cache, heap);
+    cancel_par(heap);
+
+    if 0 == cache.player_data.par {
+        cache.player_data.par = match current_block(cache, heap).par {
+            0 => heap.scene_data.num_pcs as usize + solver::PAR_FIRE_MOVES,
+            par => par,
+        };
+    }
+}
+
+
+pub fn cancel_par(heap: &mut GameDataHeap) {
+    if let Some(job) = heap.par_job.take() {
+        job.cancel();
+    }
+}
+
+
+// 3 stars at or under par, 2 within half as many moves again, 1 for any other win
+pub fn stars(moves: usize, par: usize) -> usize {
+    if 0 == par { return 1; }
+    if moves <= par { return 3; }
+    if moves <= par + par / 2 { return 2; }
+    1
+}
+
+
+// start looking for a hint in the background, see poll_hint
+pub fn request_hint(cache: &mut GameData, heap: &mut GameDataHeap) {
+
+    if heap.hint_job.is_some() { return; }
+
+    let npcs = heap.scene_data.num_pcs as usize;
+    let job = solver::HintJob::spawn(current_block(cache, heap), &heap.scene_data, cache.player_data.orient, &cache.pieces[0..npcs]);
+
+    heap.hint_job = Some(job);
+}
+
+
+// the hint once the search is done, counted against this level
+pub fn poll_hint(cache: &mut GameData, heap: &mut GameDataHeap) -> Option<solver::Hint> {
+
+    match &heap.hint_job {
+        Some(job) if job.is_finished() => (),
+        _ => return None,
+    }
+
+    let hint = heap.hint_job.take()?.join();
+    match hint {
+        Some(_) => cache.player_data.hints += 1,
+        None => println!("no hint found"),
+    }
+
+    hint
+}
+
+
+pub fn cancel_hint(heap: &mut GameDataHeap) {
+    if let Some(job) = heap.hint_job.take() {
+        job.cancel();
+    }
 }
 
 
 pub fn flip_h(cache: &mut game::GameData, heap: &mut game::GameDataHeap, world: &mut mgfw::ecs::World) {
+    mirror_board_h(cache, heap);
+    world.entity_set_tilemap(cache.tilemap_ent, cache.tileset_ent, heap.scene_data.sz, &heap.scene_data.tilemap);
+    push_history(cache, heap, Command::FlipH);
+}
+
+fn mirror_board_h(cache: &mut GameData, heap: &mut GameDataHeap) {
 
     let sz = heap.scene_data.sz;
 
@@ -273,54 +800,42 @@ pub fn flip_h(cache: &mut game::GameData, heap: &mut game::GameDataHeap, world:
             let src = yy * sz + xx;
             let dst = yy * sz + (sz - 1 - xx);
             if 0 == yy || sz-1 == yy || 0 == xx || sz-1 == xx {
-                scene.tilemap[src] = heap.scene_data.tilemap[src];
+                // arrows stay put, source colors move with the board
+                let tile = heap.scene_data.tilemap[src];
+                scene.tilemap[dst] = beam::tinted(beam::untinted(heap.scene_data.tilemap[dst]), beam::tile_color(tile));
             } else {
                 scene.tilemap[dst] = heap.scene_data.tilemap[src];
             }
         }
     }
 
-    let o = 8;
-    let o2 = o * 2;
-
-    // REFLECTOR_TL, REFLECTOR_TR, REFLECTOR_U, REFLECTOR_L, REFLECTOR_BL, REFLECTOR_BR, REFLECTOR_R, REFLECTOR_D
-    let flip_pairs = [
-        (0,    1),    (2,    2),    (3,    6),    (4,    5),    (7,    7),
-        (0+o,  1+o),  (2+o,  2+o),  (3+o,  6+o),  (4+o,  5+o),  (7+o,  7+o),
-        (0+o2, 1+o2), (2+o2, 2+o2), (3+o2, 6+o2), (4+o2, 5+o2), (7+o2, 7+o2),
-    ];
-
-    // flip pieces
-    for i in 0..flip_pairs.len() {
-        let lhs = flip_pairs[i].0;
-        let rhs = flip_pairs[i].1;
-
-        let mut lidx = cache.pieces[lhs].board_idx as usize;
-        if BOARD_IDX_INVALID != lidx as u8 {
-            let xx = lidx % sz;
-            let yy = (lidx - xx) / sz;
-            lidx = yy * sz + (sz - 1 - xx);
-        }
+    // flip pieces, each one turns into its mirror image
+    for i in 0..scene.num_pcs as usize {
+        cache.pieces[i].tile = mirror_h(cache.pieces[i].tile);
 
-        let mut ridx = cache.pieces[rhs].board_idx as usize;
-        if BOARD_IDX_INVALID != ridx as u8 {
-            let xx = ridx % sz;
-            let yy = (ridx - xx) / sz;
-            ridx = yy * sz + (sz - 1 - xx);
+        let bidx = cache.pieces[i].board_idx as usize;
+        if BOARD_IDX_INVALID as usize != bidx {
+            let xx = bidx % sz;
+            let yy = (bidx - xx) / sz;
+            cache.pieces[i].board_idx = (yy * sz + (sz - 1 - xx)) as u16;
         }
-
-        cache.pieces[lhs].board_idx = ridx as u8;
-        cache.pieces[rhs].board_idx = lidx as u8;
     }
 
-    heap.scene_data = scene;
+    // portals moved with the tilemap, pair them up again
+    scene::link_portals(&mut scene);
 
-    world.entity_set_tilemap(cache.tilemap_ent, cache.tileset_ent, heap.scene_data.sz, &heap.scene_data.tilemap);
-    push_history(cache, heap);
+    heap.scene_data = scene;
+    cache.player_data.orient = orient_flip_h(cache.player_data.orient);
 
 }
 
 pub fn flip_v(cache: &mut game::GameData, heap: &mut game::GameDataHeap, world: &mut mgfw::ecs::World) {
+    mirror_board_v(cache, heap);
+    world.entity_set_tilemap(cache.tilemap_ent, cache.tileset_ent, heap.scene_data.sz, &heap.scene_data.tilemap);
+    push_history(cache, heap, Command::FlipV);
+}
+
+fn mirror_board_v(cache: &mut GameData, heap: &mut GameDataHeap) {
 
     let sz = heap.scene_data.sz;
 
@@ -335,55 +850,43 @@ pub fn flip_v(cache: &mut game::GameData, heap: &mut game::GameDataHeap, world:
             let src = yy * sz + xx;
             let dst = (sz - 1 - yy) * sz + xx;
             if 0 == yy || sz-1 == yy || 0 == xx || sz-1 == xx {
-                scene.tilemap[src] = heap.scene_data.tilemap[src];
+                // arrows stay put, source colors move with the board
+                let tile = heap.scene_data.tilemap[src];
+                scene.tilemap[dst] = beam::tinted(beam::untinted(heap.scene_data.tilemap[dst]), beam::tile_color(tile));
             } else {
                 scene.tilemap[dst] = heap.scene_data.tilemap[src];
             }
         }
     }
 
-    let o = 8;
-    let o2 = o * 2;
-
-    // REFLECTOR_TL, REFLECTOR_TR, REFLECTOR_U, REFLECTOR_L, REFLECTOR_BL, REFLECTOR_BR, REFLECTOR_R, REFLECTOR_D
-    let flip_pairs = [
-        (0,    4),    (1,    5),    (2,    7),    (3,    3),    (6,    6),
-        (0+o,  4+o),  (1+o,  5+o),  (2+o,  7+o),  (3+o,  3+o),  (6+o,  6+o),
-        (0+o2, 4+o2), (1+o2, 5+o2), (2+o2, 7+o2), (3+o2, 3+o2), (6+o2, 6+o2),
-    ];
-
-    // flip pieces
-    for i in 0..flip_pairs.len() {
-        let lhs = flip_pairs[i].0;
-        let rhs = flip_pairs[i].1;
-
-        let mut lidx = cache.pieces[lhs].board_idx as usize;
-        if BOARD_IDX_INVALID != lidx as u8 {
-            let xx = lidx % sz;
-            let yy = (lidx - xx) / sz;
-            lidx = (sz - 1 - yy) * sz + xx;
-        }
+    // flip pieces, each one turns into its mirror image
+    for i in 0..scene.num_pcs as usize {
+        cache.pieces[i].tile = mirror_v(cache.pieces[i].tile);
 
-        let mut ridx = cache.pieces[rhs].board_idx as usize;
-        if BOARD_IDX_INVALID != ridx as u8 {
-            let xx = ridx % sz;
-            let yy = (ridx - xx) / sz;
-            ridx = (sz - 1 - yy) * sz + xx;
+        let bidx = cache.pieces[i].board_idx as usize;
+        if BOARD_IDX_INVALID as usize != bidx {
+            let xx = bidx % sz;
+            let yy = (bidx - xx) / sz;
+            cache.pieces[i].board_idx = ((sz - 1 - yy) * sz + xx) as u16;
         }
-
-        cache.pieces[lhs].board_idx = ridx as u8;
-        cache.pieces[rhs].board_idx = lidx as u8;
     }
 
-    heap.scene_data = scene;
+    // portals moved with the tilemap, pair them up again
+    scene::link_portals(&mut scene);
 
-    world.entity_set_tilemap(cache.tilemap_ent, cache.tileset_ent, heap.scene_data.sz, &heap.scene_data.tilemap);
-    push_history(cache, heap);
+    heap.scene_data = scene;
+    cache.player_data.orient = orient_flip_v(cache.player_data.orient);
 
 }
 
 
 pub fn rotate(cache: &mut game::GameData, heap: &mut game::GameDataHeap, world: &mut mgfw::ecs::World) {
+    rotate_board(cache, heap);
+    world.entity_set_tilemap(cache.tilemap_ent, cache.tileset_ent, heap.scene_data.sz, &heap.scene_data.tilemap);
+    push_history(cache, heap, Command::Rotate);
+}
+
+fn rotate_board(cache: &mut GameData, heap: &mut GameDataHeap) {
 
     let sz = heap.scene_data.sz;
     let sz2 = heap.scene_data.sz2;
@@ -404,7 +907,9 @@ pub fn rotate(cache: &mut game::GameData, heap: &mut game::GameDataHeap, world:
             let dst = y1 * sz + x1;
 
             if 0 == yy || sz-1 == yy || 0 == xx || sz-1 == xx {
-                tiles[src] = heap.scene_data.tilemap[src];
+                // arrows stay put, source colors move with the board
+                let tile = heap.scene_data.tilemap[src];
+                tiles[dst] = beam::tinted(beam::untinted(heap.scene_data.tilemap[dst]), beam::tile_color(tile));
             } else {
                 tiles[dst] = heap.scene_data.tilemap[src];
             }
@@ -417,205 +922,331 @@ pub fn rotate(cache: &mut game::GameData, heap: &mut game::GameDataHeap, world:
     }
 
 
-    let o = 8;
-    let o2 = o * 2;
-
-    // REFLECTOR_TL, REFLECTOR_TR, REFLECTOR_U, REFLECTOR_L, REFLECTOR_BL, REFLECTOR_BR, REFLECTOR_R, REFLECTOR_D
-    let flip_pairs = [
-        (0,    1),    (1,    5),    (5,    4),    (4,    0),    (2,    6),    (6,    7),    (7,    3),    (3,    2),
-        (0+o,  1+o),  (1+o,  5+o),  (5+o,  4+o),  (4+o,  0+o),  (2+o,  6+o),  (6+o,  7+o),  (7+o,  3+o),  (3+o,  2+o),
-        (0+o2, 1+o2), (1+o2, 5+o2), (5+o2, 4+o2), (4+o2, 0+o2), (2+o2, 6+o2), (6+o2, 7+o2), (7+o2, 3+o2), (3+o2, 2+o2),
-    ];
-
-    // destination set
-    let mut pieces =[BOARD_IDX_INVALID; MAX_PIECES];
-
-    // first assign rotated board indexes
-    for i in 0..flip_pairs.len() {
-        let lhs = flip_pairs[i].0;
-        let rhs = flip_pairs[i].1;
-        pieces[rhs] = cache.pieces[lhs].board_idx;
-    }
-
-    // then rotate positions
+    // rotate pieces, each one turns with the board
     for i in 0..scene.num_pcs as usize {
-        let bidx = pieces[i] as usize;
+        cache.pieces[i].tile = rotated(cache.pieces[i].tile);
+
+        let bidx = cache.pieces[i].board_idx as usize;
         if BOARD_IDX_INVALID as usize != bidx {
             let x0 = bidx % sz;
             let y0 = (bidx - x0) / sz;
             let x1 = sz - 1 - y0;
             let y1 = x0;
-            pieces[i] = (y1 * sz + x1) as u8;
+            cache.pieces[i].board_idx = (y1 * sz + x1) as u16;
         }
-
-        cache.pieces[i].board_idx = pieces[i];
     }
 
+    // portals moved with the tilemap, pair them up again
+    scene::link_portals(&mut scene);
+
     heap.scene_data = scene;
+    cache.player_data.orient = orient_rotate(cache.player_data.orient);
 
-    world.entity_set_tilemap(cache.tilemap_ent, cache.tileset_ent, heap.scene_data.sz, &heap.scene_data.tilemap);
-    push_history(cache, heap);
+}
 
+
+// orientation as a mirror then turns, so a mirror after r turns is the
+// mirror followed by 4 - r turns and flip_v is flip_h followed by two turns
+fn orient_flip_h(orient: u8) -> u8 {
+    (1 - orient / 4) * 4 + (4 - orient % 4) % 4
 }
 
+fn orient_flip_v(orient: u8) -> u8 {
+    (1 - orient / 4) * 4 + (6 - orient % 4) % 4
+}
+
+fn orient_rotate(orient: u8) -> u8 {
+    orient / 4 * 4 + (orient + 1) % 4
+}
+
+
+// a piece of the level's inventory as it is on a board turned to orient
+fn oriented_piece(tile: u16, orient: u8) -> u16 {
+    let mut tile = if 4 <= orient { mirror_h(tile) } else { tile };
+    for _ in 0..orient % 4 {
+        tile = rotated(tile);
+    }
+    tile
+}
 
-fn decompress(txt: String) -> String {
-    let mut out = txt;
-    
-    out = out.replace("\"", "lA");
-    out = out.replace("/", ".&");
-    out = out.replace("|", ".i");
-    out = out.replace("`", "cB");
-    out = out.replace("?", "~~");
-    out = out.replace(">", ".z");
-    out = out.replace("<", ".h");
-    out = out.replace(";", "#A");
-    out = out.replace(":", ".F");
-    out = out.replace("}", "kA");
-    out = out.replace("{", "jA");
-    out = out.replace("]", "bB");
-    out = out.replace("[", ".g");
-    out = out.replace("=", "$A");
-    out = out.replace("_", "aB");
-    out = out.replace("0", ".!");
-    out = out.replace("9", ".f");
-    out = out.replace("8", "hA");
-    out = out.replace("7", "iA");
-    out = out.replace("6", ".E");
-    out = out.replace("5", ".D");
-    out = out.replace("4", ".~");
-    out = out.replace("2", "gA");
-    out = out.replace("1", ".e");
-    out = out.replace("+", ".a");
-    out = out.replace("-", "fA");
-    out = out.replace(")", "dA");
-    out = out.replace("(", ".C");
-    out = out.replace("*", "cA");
-    out = out.replace("&", "eA");
-    out = out.replace("^", ".B");
-    out = out.replace("%", ".b");
-    out = out.replace("$", ".d");
-    out = out.replace("#", ".c");
-    out = out.replace("@", ".A");
-    out = out.replace("!", "bA");
-    out = out.replace("~", "aA");
-
-    out
-}
-
-fn expand(txt: String) -> Vec<u8> {
-
-    let mut out = txt;
-
-    out = out.replace(".", "_");
-
-    let mut rhs = String::new();
-    for i in 1..27 {
-        let lhs = char::from(96 + i);
-        rhs = format!("{rhs}.");
-        out = out.replace(lhs, rhs.as_str());
-    }
-
-    let mut rhs = String::new();
-    for i in 1..27 {
-        let lhs = char::from(64 + i);
-        rhs = format!("{rhs}x");
-        out = out.replace(lhs, rhs.as_str());
-    }
-
-    out = out.replace("_", "o");
-
-    out.as_bytes().to_vec()
-}
-
-
-pub fn place_piece(cache: &mut GameData, heap: &mut GameDataHeap, holding_idx: u8, board_idx: u8) {
 
+// reflector a piece becomes when the board is mirrored left to right
+fn mirror_h(tile: u16) -> u16 {
+    match tile {
+        REFLECTOR_TL => REFLECTOR_TR,
+        REFLECTOR_TR => REFLECTOR_TL,
+        REFLECTOR_BL => REFLECTOR_BR,
+        REFLECTOR_BR => REFLECTOR_BL,
+        REFLECTOR_L => REFLECTOR_R,
+        REFLECTOR_R => REFLECTOR_L,
+        _ => tile,
+    }
+}
+
+// reflector a piece becomes when the board is mirrored top to bottom
+fn mirror_v(tile: u16) -> u16 {
+    match tile {
+        REFLECTOR_TL => REFLECTOR_BL,
+        REFLECTOR_BL => REFLECTOR_TL,
+        REFLECTOR_TR => REFLECTOR_BR,
+        REFLECTOR_BR => REFLECTOR_TR,
+        REFLECTOR_U => REFLECTOR_D,
+        REFLECTOR_D => REFLECTOR_U,
+        _ => tile,
+    }
+}
+
+// reflector a piece becomes when the board is rotated clockwise
+fn rotated(tile: u16) -> u16 {
+    match tile {
+        REFLECTOR_TL => REFLECTOR_TR,
+        REFLECTOR_TR => REFLECTOR_BR,
+        REFLECTOR_BR => REFLECTOR_BL,
+        REFLECTOR_BL => REFLECTOR_TL,
+        REFLECTOR_U => REFLECTOR_R,
+        REFLECTOR_R => REFLECTOR_D,
+        REFLECTOR_D => REFLECTOR_L,
+        REFLECTOR_L => REFLECTOR_U,
+        _ => tile,
+    }
+}
+
+
+pub fn place_piece(cache: &mut GameData, heap: &mut GameDataHeap, holding_idx: u8, board_idx: u16) {
+
+    let from = cache.pieces[holding_idx as usize].board_idx;
     cache.pieces[holding_idx as usize].board_idx = board_idx;
-    push_history(cache, heap);
+    cache.player_data.moves += 1;
+    push_history(cache, heap, Command::Place { piece: holding_idx, from, to: board_idx });
 }
 
 
-pub fn swap_piece(cache: &mut GameData, heap: &mut GameDataHeap, holding_idx: u8, board_idx: u8, swapping_idx: usize) {
+// false if the piece on the board is locked and has to stay
+pub fn swap_piece(cache: &mut GameData, heap: &mut GameDataHeap, holding_idx: u8, board_idx: u16, swapping_idx: usize) -> bool {
 
+    if cache.pieces[swapping_idx].locked { return false; }
+
+    let from = cache.pieces[holding_idx as usize].board_idx;
     cache.pieces[holding_idx as usize].board_idx = board_idx;
     cache.pieces[swapping_idx].board_idx = BOARD_IDX_INVALID;
-    push_history(cache, heap);
+    cache.player_data.moves += 1;
+    push_history(cache, heap, Command::Swap { piece: holding_idx, from, to: board_idx, swapped: swapping_idx as u8 });
+    true
 }
 
 
-pub fn pickup_piece(cache: &mut GameData, heap: &mut GameDataHeap, pickup_idx: usize) {
-    
+// false if the piece is locked and has to stay
+pub fn pickup_piece(cache: &mut GameData, heap: &mut GameDataHeap, pickup_idx: usize) -> bool {
+
+    if cache.pieces[pickup_idx].locked { return false; }
+
+    let from = cache.pieces[pickup_idx].board_idx;
     cache.pieces[pickup_idx].board_idx = BOARD_IDX_INVALID;
-    push_history(cache, heap);
+    cache.player_data.moves += 1;
+    push_history(cache, heap, Command::Pickup { piece: pickup_idx as u8, from });
+    true
 }
 
 
-fn push_history(cache: &mut GameData, heap: &mut GameDataHeap) {
+// take a piece off the board for a drag without recording it, the drop records
+// the whole move. false if the piece is locked and has to stay
+pub fn lift_piece(cache: &mut GameData, lift_idx: usize) -> bool {
 
-    let mut h = HistoryData::new();
-    
-    // copy piece information
-    for i in 0..MAX_PIECES {
-        h.pieces[i] = cache.pieces[i].board_idx;
-    }
+    if cache.pieces[lift_idx].locked { return false; }
 
-    // copy tile information
-    for i in 0..heap.scene_data.tilemap.len() {
-        h.tilemap[i] = heap.scene_data.tilemap[i];
-    }
+    cache.pieces[lift_idx].board_idx = BOARD_IDX_INVALID;
+    true
+}
 
-    // trim history tail
-    for _ in (cache.history_idx+1)..heap.history.len() {
-        heap.history.pop();
-    }
+// put a lifted piece back on the cell it came from, nothing happened to record
+pub fn unlift_piece(cache: &mut GameData, lift_idx: usize, board_idx: u16) {
+    cache.pieces[lift_idx].board_idx = board_idx;
+}
 
-    heap.history.push(h);
-    cache.history_idx = heap.history.len() - 1;
 
+// record the command just played below the current node
+fn push_history(cache: &mut GameData, heap: &mut GameDataHeap, cmd: Command) {
+    cache.history_idx = history::push(&mut heap.history, cache.history_idx, cmd);
 }
 
 fn clear_history(cache: &mut GameData, heap: &mut GameDataHeap) {
-    heap.history.clear();
-    cache.history_idx = 0;
+    history::clear(&mut heap.history);
+    cache.history_idx = history::NODE_ROOT;
 }
 
 pub fn history_undo(cache: &mut game::GameData, heap: &mut game::GameDataHeap, world: &mut mgfw::ecs::World) {
 
-    if cache.history_idx == 0 { return; }
+    if history::NODE_ROOT == cache.history_idx { return; }
 
-    // move history pointer
-    cache.history_idx -= 1;
+    step_up(cache, heap);
 
-    // copy piece information
-    for i in 0..MAX_PIECES {
-        cache.pieces[i].board_idx = heap.history[cache.history_idx].pieces[i];
-    }
+    world.entity_set_tilemap(cache.tilemap_ent, cache.tileset_ent, heap.scene_data.sz, &heap.scene_data.tilemap);
+}
 
-    // copy tile information
-    for i in 0..heap.scene_data.tilemap.len() {
-        heap.scene_data.tilemap[i] = heap.history[cache.history_idx].tilemap[i];
+pub fn history_redo(cache: &mut game::GameData, heap: &mut game::GameDataHeap, world: &mut mgfw::ecs::World) {
+
+    if history::NODE_ROOT == history::redo(&heap.history, cache.history_idx) { return; }
+
+    step_down(cache, heap);
+
+    world.entity_set_tilemap(cache.tilemap_ent, cache.tileset_ent, heap.scene_data.sz, &heap.scene_data.tilemap);
+}
+
+
+// branch the board is on at the last fork and how many there are, None before any fork
+pub fn history_branch_info(cache: &GameData, heap: &GameDataHeap) -> Option<(usize, usize)> {
+    history::branch(&heap.history, cache.history_idx)
+}
+
+
+// swap to the next (or previous) branch at the last fork, as far down it as it was played
+pub fn history_branch(cache: &mut game::GameData, heap: &mut game::GameDataHeap, world: &mut mgfw::ecs::World, step: isize) {
+
+    let fork = match history::fork(&heap.history, cache.history_idx) {
+        Some(fork) => fork,
+        None => return,
+    };
+
+    let parent = heap.history.nodes[fork].parent;
+    let siblings = history::children(&heap.history, parent);
+    let k = siblings.iter().position(|n| fork == *n).unwrap_or(0) as isize;
+    let target = siblings[(k + step).rem_euclid(siblings.len() as isize) as usize];
+
+    // back up to the fork, then down the other branch
+    while parent != cache.history_idx {
+        step_up(cache, heap);
+    }
+    history::set_redo(&mut heap.history, parent, target);
+    while history::NODE_ROOT != history::redo(&heap.history, cache.history_idx) {
+        step_down(cache, heap);
     }
 
     world.entity_set_tilemap(cache.tilemap_ent, cache.tileset_ent, heap.scene_data.sz, &heap.scene_data.tilemap);
 }
 
-pub fn history_redo(cache: &mut game::GameData, heap: &mut game::GameDataHeap, world: &mut mgfw::ecs::World) {
 
-    if cache.history_idx == heap.history.len() - 1 { return; }
+// undo the current node, redo comes back to it
+fn step_up(cache: &mut GameData, heap: &mut GameDataHeap) {
+    let cmd = heap.history.nodes[cache.history_idx].cmd.clone();
+    let parent = heap.history.nodes[cache.history_idx].parent;
+    undo_command(cache, heap, &cmd);
+    history::set_redo(&mut heap.history, parent, cache.history_idx);
+    cache.history_idx = parent;
+}
 
-    // move history pointer
-    cache.history_idx += 1;
+fn step_down(cache: &mut GameData, heap: &mut GameDataHeap) {
+    let next = history::redo(&heap.history, cache.history_idx);
+    let cmd = heap.history.nodes[next].cmd.clone();
+    apply_command(cache, heap, &cmd);
+    cache.history_idx = next;
+}
 
-    // copy piece information
-    for i in 0..MAX_PIECES {
-        cache.pieces[i].board_idx = heap.history[cache.history_idx].pieces[i];
+fn apply_command(cache: &mut GameData, heap: &mut GameDataHeap, cmd: &Command) {
+    match *cmd {
+        Command::Place { piece, to, .. } => cache.pieces[piece as usize].board_idx = to,
+        Command::Swap { piece, to, swapped, .. } => {
+            cache.pieces[piece as usize].board_idx = to;
+            cache.pieces[swapped as usize].board_idx = BOARD_IDX_INVALID;
+        },
+        Command::Pickup { piece, .. } => cache.pieces[piece as usize].board_idx = BOARD_IDX_INVALID,
+        Command::FlipH => mirror_board_h(cache, heap),
+        Command::FlipV => mirror_board_v(cache, heap),
+        Command::Rotate => rotate_board(cache, heap),
+        Command::Reset { .. } => scene::reset_scene(cache, heap),
+    }
+}
+
+fn undo_command(cache: &mut GameData, heap: &mut GameDataHeap, cmd: &Command) {
+    match cmd {
+        Command::Place { piece, from, .. } => cache.pieces[*piece as usize].board_idx = *from,
+        Command::Swap { piece, from, to, swapped } => {
+            cache.pieces[*piece as usize].board_idx = *from;
+            cache.pieces[*swapped as usize].board_idx = *to;
+        },
+        Command::Pickup { piece, from } => cache.pieces[*piece as usize].board_idx = *from,
+        Command::FlipH => mirror_board_h(cache, heap),
+        Command::FlipV => mirror_board_v(cache, heap),
+        Command::Rotate => {
+            // three more turns come back round
+            for _ in 0..3 {
+                rotate_board(cache, heap);
+            }
+        },
+        Command::Reset { pieces } => {
+            let npcs = heap.scene_data.num_pcs as usize;
+            for (p, board_idx) in cache.pieces[0..npcs].iter_mut().zip(pieces.iter()) {
+                p.board_idx = *board_idx;
+            }
+        },
+    }
+}
+
+
+// the commands that led to the board as it is, oldest first
+pub fn move_log(cache: &GameData, heap: &GameDataHeap) -> String {
+    history::path(&heap.history, cache.history_idx).iter().map(|n| history::encode(&heap.history.nodes[*n].cmd)).collect::<Vec<String>>().join(", ")
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rand::rngs::StdRng;
+
+    // a saved board after the turns and mirrors a player makes
+    fn played(block: &Block, ops: &[u8]) -> savegame::SavedBoard {
+        let mut cache: Box<GameData> = unsafe { Box::new_zeroed().assume_init() };
+        let mut heap = GameDataHeap { scene_data: scene::build_scene(block, &mut StdRng::seed_from_u64(7)), ..Default::default() };
+
+        scene::lock_pieces(block, &mut cache.pieces[0..block.npcs]);
+        for op in ops {
+            match op {
+                b'h' => mirror_board_h(&mut cache, &mut heap),
+                b'v' => mirror_board_v(&mut cache, &mut heap),
+                _ => rotate_board(&mut cache, &mut heap),
+            }
+        }
+
+        savegame::SavedBoard {
+            orient: cache.player_data.orient,
+            tilemap: heap.scene_data.tilemap.clone(),
+            pieces: cache.pieces[0..block.npcs].to_vec(),
+            ..Default::default()
+        }
     }
 
-    // copy tile information
-    for i in 0..heap.scene_data.tilemap.len() {
-        heap.scene_data.tilemap[i] = heap.history[cache.history_idx].tilemap[i];
+    #[test]
+    fn saved_boards_fit_their_level_in_every_orientation() {
+        let blocks = levels::load_blocks("assets/levels.dat").unwrap();
+        let picked = blocks.iter().enumerate().filter(|(i, b)| 0 == i % 40 || b.map.iter().any(|c| levels::is_portal_cell(*c) || levels::is_filter_cell(*c))).map(|(_, b)| b);
+
+        for block in picked {
+            for ops in ["", "r", "rr", "rrr", "h", "hr", "v", "vr", "rhvr"] {
+                assert!(fits_level(block, &played(block, ops.as_bytes())), "orientation {ops}");
+            }
+        }
     }
 
-    world.entity_set_tilemap(cache.tilemap_ent, cache.tileset_ent, heap.scene_data.sz, &heap.scene_data.tilemap);
-}
\ No newline at end of file
+    #[test]
+    fn saved_boards_off_their_level_are_rejected() {
+        let blocks = levels::load_blocks("assets/levels.dat").unwrap();
+        let block = &blocks[0];
+
+        // one piece swapped for another kind
+        let mut saved = played(block, b"r");
+        saved.pieces[0].tile = if REFLECTOR_TL == saved.pieces[0].tile { REFLECTOR_R } else { REFLECTOR_TL };
+        assert!(!fits_level(block, &saved));
+
+        // a piece short
+        let mut saved = played(block, b"r");
+        saved.pieces.pop();
+        assert!(!fits_level(block, &saved));
+
+        // an orb paved over
+        let mut saved = played(block, b"h");
+        let orb = saved.tilemap.iter().position(|t| beam::is_orb(*t)).unwrap();
+        saved.tilemap[orb] = FLOOR;
+        assert!(!fits_level(block, &saved));
+    }
+}
@@ -0,0 +1,192 @@
+use super::beam;
+use super::enums::*;
+use super::game_state::PieceData;
+use super::levels;
+use super::levels::Block;
+use super::scene;
+
+use rand::prelude::*;
+
+// attempts per block before giving up on a size/piece/orb combination
+pub const GENERATOR_MAX_TRIES: usize = 2000;
+
+// attempts at growing the beam by one more piece
+const GROW_TRIES: usize = 40;
+
+
+// build a new level block of the given size, piece count and orb count.
+// a beam is grown out from a random source one piece at a time, then the
+// orbs go on cells it lights. the cells the beam crosses become the
+// designer path so the random block scatter can never cut it.
+pub fn generate(sz: usize, npcs: usize, norbs: usize, rng: &mut impl Rng) -> Option<Block> {
+
+    if !in_range(sz, npcs, norbs) { return None; }
+
+    let mut block = Block::empty();
+    block.sz = sz;
+    block.npcs = npcs;
+    block.pieces = levels::standard_pieces(npcs);
+    block.listed = levels::needs_piece_list(npcs);
+    block.norbs = norbs;
+    block.map = vec![CELL_FLOOR; sz * sz];
+
+    for _ in 0..GENERATOR_MAX_TRIES {
+        if let Some(map) = try_generate(&block, rng) {
+            block.map = map;
+            return Some(block);
+        }
+    }
+
+    None
+}
+
+
+// sizes, piece counts and orb counts generate will try
+pub fn in_range(sz: usize, npcs: usize, norbs: usize) -> bool {
+    (3..=MAP_MAX_WIDTH).contains(&sz) && (1..=MAX_PIECES).contains(&npcs) && 0 < norbs
+}
+
+
+fn try_generate(block: &Block, rng: &mut impl Rng) -> Option<Vec<u8>> {
+
+    let mut scene = scene::build_scene_empty(block);
+    let sz2 = scene.sz2;
+
+    let sources = beam::sources(&scene);
+    let source = sources[rng.gen_range(0..sources.len())];
+
+    // pieces left to place, in random order
+    let mut bag = block.pieces.clone();
+    bag.shuffle(rng);
+
+    // use most of the inventory so bigger sets give longer beams
+    let target = match block.npcs {
+        0 => 0,
+        n => rng.gen_range(n.div_ceil(2)..=n),
+    };
+
+    let mut pieces: Vec<PieceData> = Vec::new();
+    let mut trace = beam::trace(&scene, &pieces, source);
+
+    while pieces.len() < target && !bag.is_empty() {
+        let mut grown = false;
+
+        for _ in 0..GROW_TRIES {
+            let (idx, _) = trace.path[rng.gen_range(0..trace.path.len())];
+            if !scene::is_floor(scene.tilemap[idx]) || pieces.iter().any(|p| idx == p.board_idx as usize) { continue; }
+
+            let k = rng.gen_range(0..bag.len());
+            pieces.push(PieceData { tile: bag[k], board_idx: idx as u16, active: false, home: 0, locked: false });
+
+            // the new piece has to be lit and light up something new
+            let next = beam::trace(&scene, &pieces, source);
+            if all_lit(&next.beams, &pieces) && next.path.len() > trace.path.len() {
+                bag.swap_remove(k);
+                trace = next;
+                grown = true;
+                break;
+            }
+            pieces.pop();
+        }
+
+        if !grown { break; }
+    }
+
+    // orbs stop the beam, so favour cells late in the trace
+    let mut cells: Vec<usize> = Vec::new();
+    for &(idx, _) in &trace.path[trace.path.len() / 2..] {
+        if scene::is_floor(scene.tilemap[idx]) && !pieces.iter().any(|p| idx == p.board_idx as usize) && !cells.contains(&idx) {
+            cells.push(idx);
+        }
+    }
+    if cells.len() < block.norbs { return None; }
+
+    cells.shuffle(rng);
+    for &idx in &cells[0..block.norbs] {
+        scene.tilemap[idx] = ORB;
+    }
+    scene.num_goals = block.norbs as u8;
+
+    // every orb lit and every piece still in use
+    let trace = beam::trace(&scene, &pieces, source);
+    if block.norbs != trace.lit_orbs.len() || !all_lit(&trace.beams, &pieces) { return None; }
+
+    // too easy if a bare beam already does it
+    for &s in &sources {
+        if block.norbs == beam::trace(&scene, &Vec::new(), s).lit_orbs.len() { return None; }
+    }
+
+    let mut map = vec![CELL_FLOOR; sz2];
+    for &(idx, _) in &trace.path {
+        if scene::is_floor(scene.tilemap[idx]) {
+            map[idx] = CELL_PATH;
+        }
+    }
+    for &idx in &cells[0..block.norbs] {
+        map[idx] = CELL_ORB;
+    }
+
+    Some(map)
+}
+
+
+fn all_lit(beams: &scene::TileMap, pieces: &[PieceData]) -> bool {
+    pieces.iter().all(|p| 0 != beams[p.board_idx as usize])
+}
+
+
+// generate count blocks and print them as levels.dat lines, false if
+// the arguments are out of range or a block couldn't be found
+pub fn report(sz: usize, npcs: usize, norbs: usize, count: usize) -> bool {
+
+    if !in_range(sz, npcs, norbs) {
+        eprintln!("sz has to be 3..={MAP_MAX_WIDTH}, npcs 1..={MAX_PIECES} and norbs at least 1");
+        return false;
+    }
+
+    let mut rng = rand::thread_rng();
+
+    for _ in 0..count {
+        match generate(sz, npcs, norbs, &mut rng) {
+            Some(block) => println!("{}", levels::encode_block(&block)),
+            None => {
+                eprintln!("no level found for sz {sz}, npcs {npcs}, norbs {norbs}");
+                return false;
+            }
+        }
+    }
+
+    true
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand_chacha::ChaCha8Rng;
+
+    #[test]
+    fn generated_lines_parse_and_lint_clean() {
+        let mut rng = ChaCha8Rng::seed_from_u64(7);
+
+        // a count off the 8 piece cycle has to list its pieces
+        for npcs in [4, 7, 8] {
+            let block = generate(7, npcs, 2, &mut rng).unwrap();
+            let line = levels::encode_block(&block);
+
+            let back = levels::parse_block(&line).unwrap();
+            assert_eq!(block.pieces, back.pieces);
+            assert_eq!(block.map, back.map);
+            assert!(levels::lint_block(&back).is_empty(), "{line}: {:?}", levels::lint_block(&back));
+        }
+    }
+
+    #[test]
+    fn out_of_range_arguments_are_rejected() {
+        let mut rng = ChaCha8Rng::seed_from_u64(7);
+        assert!(generate(7, 0, 2, &mut rng).is_none());
+        assert!(generate(7, MAX_PIECES + 1, 2, &mut rng).is_none());
+        assert!(generate(2, 8, 2, &mut rng).is_none());
+        assert!(generate(7, 8, 0, &mut rng).is_none());
+    }
+}
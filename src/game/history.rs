@@ -0,0 +1,238 @@
+use super::enums::*;
+
+// one step of the undo history. a command can be done again or taken back,
+// so a line of play is the commands from the board as it was laid out and
+// doubles as the move log. board idxs are as the board was when it was played
+#[derive(Clone, PartialEq)]
+pub enum Command {
+    Place { piece: u8, from: u16, to: u16 },
+    Swap { piece: u8, from: u16, to: u16, swapped: u8 }, // the swapped piece leaves to
+    Pickup { piece: u8, from: u16 },
+    FlipH,
+    FlipV,
+    Rotate,
+    Reset { pieces: Box<[u16]> }, // where every piece was before, boxed to keep the other commands small
+}
+
+
+// the board as it was laid out, above every first move
+pub const NODE_ROOT: usize = usize::MAX;
+
+#[derive(Clone)]
+pub struct Node {
+    pub cmd: Command,
+    pub parent: usize,
+    pub redo: usize, // child redo goes to, the one played or visited last. NODE_ROOT for none
+    children: Vec<usize>, // in the order they were first played
+}
+
+// every line of play tried on the board. a move made after an undo starts a
+// new branch next to the old one instead of dropping it
+pub struct UndoTree {
+    pub nodes: Vec<Node>,
+    pub redo_root: usize, // redo from the laid out board
+    children_root: Vec<usize>,
+}
+
+impl UndoTree {
+    pub fn new() -> UndoTree {
+        UndoTree {
+            nodes: Vec::new(),
+            redo_root: NODE_ROOT,
+            children_root: Vec::new(),
+        }
+    }
+}
+
+impl Default for UndoTree {
+    fn default() -> Self {
+        UndoTree::new()
+    }
+}
+
+
+pub fn clear(tree: &mut UndoTree) {
+    tree.nodes.clear();
+    tree.redo_root = NODE_ROOT;
+    tree.children_root.clear();
+}
+
+
+// a new node below parent, redo is left for the caller
+pub fn add_node(tree: &mut UndoTree, parent: usize, cmd: Command) -> usize {
+    let n = tree.nodes.len();
+    tree.nodes.push(Node { cmd, parent, redo: NODE_ROOT, children: Vec::new() });
+    match parent {
+        NODE_ROOT => tree.children_root.push(n),
+        _ if n > parent => tree.nodes[parent].children.push(n),
+        _ => (),
+    }
+    n
+}
+
+
+// the node for playing cmd at a node, replaying a move already tried goes
+// back down its branch rather than starting another one
+pub fn push(tree: &mut UndoTree, at: usize, cmd: Command) -> usize {
+    let node = match children(tree, at).iter().find(|n| cmd == tree.nodes[**n].cmd) {
+        Some(n) => *n,
+        None => add_node(tree, at, cmd),
+    };
+    set_redo(tree, at, node);
+    node
+}
+
+
+pub fn redo(tree: &UndoTree, at: usize) -> usize {
+    match at {
+        NODE_ROOT => tree.redo_root,
+        _ => tree.nodes[at].redo,
+    }
+}
+
+pub fn set_redo(tree: &mut UndoTree, at: usize, child: usize) {
+    match at {
+        NODE_ROOT => tree.redo_root = child,
+        _ => tree.nodes[at].redo = child,
+    }
+}
+
+
+// in the order they were first played
+pub fn children(tree: &UndoTree, at: usize) -> &[usize] {
+    match at {
+        NODE_ROOT => &tree.children_root,
+        _ => &tree.nodes[at].children,
+    }
+}
+
+
+// the moves from the laid out board to a node, oldest first
+pub fn path(tree: &UndoTree, at: usize) -> Vec<usize> {
+    let mut ret = Vec::new();
+    let mut n = at;
+    while NODE_ROOT != n {
+        ret.push(n);
+        n = tree.nodes[n].parent;
+    }
+    ret.reverse();
+    ret
+}
+
+
+// the move where the line of play to a node last split off from another
+// branch, None if it never did
+pub fn fork(tree: &UndoTree, at: usize) -> Option<usize> {
+    let mut n = at;
+    while NODE_ROOT != n {
+        let parent = tree.nodes[n].parent;
+        if 1 < children(tree, parent).len() { return Some(n); }
+        n = parent;
+    }
+    None
+}
+
+
+// the branch played at the last fork and how many there are to pick from
+pub fn branch(tree: &UndoTree, at: usize) -> Option<(usize, usize)> {
+    let n = fork(tree, at)?;
+    let siblings = children(tree, tree.nodes[n].parent);
+    Some((siblings.iter().position(|s| n == *s)?, siblings.len()))
+}
+
+
+// false unless every node sits below an earlier one and every redo is a child
+pub fn is_valid(tree: &UndoTree, at: usize) -> bool {
+    let node = |n: usize| NODE_ROOT == n || tree.nodes.len() > n;
+    let child = |n: usize, parent: usize| NODE_ROOT == n || (tree.nodes.len() > n && parent == tree.nodes[n].parent);
+
+    node(at) && child(tree.redo_root, NODE_ROOT) && (0..tree.nodes.len()).all(|n| {
+        let p = tree.nodes[n].parent;
+        (NODE_ROOT == p || n > p) && child(tree.nodes[n].redo, n)
+    })
+}
+
+
+// place <piece> <from> <to>, swap <piece> <from> <to> <swapped>, pickup <piece> <from>,
+// flip_h, flip_v, rotate and reset <board idxs..>. an empty board idx is written as -
+pub fn encode(cmd: &Command) -> String {
+    match cmd {
+        Command::Place { piece, from, to } => format!("place {piece} {} {}", idx_text(*from), idx_text(*to)),
+        Command::Swap { piece, from, to, swapped } => format!("swap {piece} {} {} {swapped}", idx_text(*from), idx_text(*to)),
+        Command::Pickup { piece, from } => format!("pickup {piece} {}", idx_text(*from)),
+        Command::FlipH => String::from("flip_h"),
+        Command::FlipV => String::from("flip_v"),
+        Command::Rotate => String::from("rotate"),
+        Command::Reset { pieces } => format!("reset {}", pieces.iter().map(|p| idx_text(*p)).collect::<Vec<String>>().join(" ")),
+    }
+}
+
+
+pub fn parse(txt: &str) -> Option<Command> {
+
+    let split: Vec<&str> = txt.split_whitespace().collect();
+    let piece = |i: usize| split.get(i).and_then(|v| v.parse::<u8>().ok()).filter(|p| MAX_PIECES > *p as usize);
+    let idx = |i: usize| split.get(i).and_then(|v| parse_idx(v));
+
+    let cmd = match *split.first()? {
+        "place" => Command::Place { piece: piece(1)?, from: idx(2)?, to: idx(3)? },
+        "swap" => Command::Swap { piece: piece(1)?, from: idx(2)?, to: idx(3)?, swapped: piece(4)? },
+        "pickup" => Command::Pickup { piece: piece(1)?, from: idx(2)? },
+        "flip_h" => Command::FlipH,
+        "flip_v" => Command::FlipV,
+        "rotate" => Command::Rotate,
+        "reset" => {
+            if MAX_PIECES + 1 < split.len() { return None; }
+            let pieces = (1..split.len()).map(idx).collect::<Option<Box<[u16]>>>()?;
+            Command::Reset { pieces }
+        },
+        _ => return None,
+    };
+
+    Some(cmd)
+}
+
+
+// false if the command names a piece or cell the board doesn't have
+pub fn fits(cmd: &Command, npcs: usize, sz2: usize) -> bool {
+    let piece = |p: u8| npcs > p as usize;
+    let idx = |i: u16| BOARD_IDX_INVALID == i || sz2 > i as usize;
+    match cmd {
+        Command::Place { piece: p, from, to } => piece(*p) && idx(*from) && idx(*to),
+        Command::Swap { piece: p, from, to, swapped } => piece(*p) && idx(*from) && idx(*to) && piece(*swapped),
+        Command::Pickup { piece: p, from } => piece(*p) && idx(*from),
+        Command::Reset { pieces } => npcs == pieces.len() && pieces.iter().all(|i| idx(*i)),
+        _ => true,
+    }
+}
+
+
+// a node as written to the save file, NODE_ROOT as -
+pub fn node_text(n: usize) -> String {
+    match n {
+        NODE_ROOT => String::from("-"),
+        _ => n.to_string(),
+    }
+}
+
+pub fn parse_node(txt: &str) -> Option<usize> {
+    match txt {
+        "-" => Some(NODE_ROOT),
+        _ => txt.parse::<usize>().ok().filter(|n| NODE_ROOT != *n),
+    }
+}
+
+
+fn idx_text(idx: u16) -> String {
+    match idx {
+        BOARD_IDX_INVALID => String::from("-"),
+        _ => idx.to_string(),
+    }
+}
+
+fn parse_idx(txt: &str) -> Option<u16> {
+    match txt {
+        "-" => Some(BOARD_IDX_INVALID),
+        _ => txt.parse::<u16>().ok().filter(|i| BOARD_IDX_INVALID != *i),
+    }
+}
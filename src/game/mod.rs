@@ -1,7 +1,16 @@
+mod beam;
 mod game;
-pub mod enums;
+pub use mirrorb::enums;
 pub mod game_state;
+pub mod generator;
+mod history;
+pub use mirrorb::levels;
+mod progress;
+mod savegame;
 mod scene;
+mod share;
+mod speedrun;
+pub mod solver;
 mod ui;
 
 use crate::mgfw;
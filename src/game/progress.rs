@@ -0,0 +1,105 @@
+use std::fs::File;
+use std::io::{self, BufRead, Write};
+
+pub const PROGRESS_FILE: &str = "progress.dat";
+
+// campaign progress, completed levels are indexed by displayed level - 1
+#[derive(Default, Clone)]
+pub struct Progress {
+    pub level: usize, // board to continue from, 0 when there is nothing to continue
+    pub sub_level: usize,
+    pub level_option: usize,
+    pub completed: Vec<bool>,
+    pub stars: Vec<usize>, // best stars on each completed level, 0 for levels not won
+}
+
+
+// remember the board being played so continue can come back to it
+pub fn resume_at(data: &mut Progress, level: usize, sub_level: usize, level_option: usize) {
+    data.level = level;
+    data.sub_level = sub_level;
+    data.level_option = level_option;
+}
+
+
+// mark a displayed level won, keeping the best stars, the final level ends the campaign
+pub fn complete(data: &mut Progress, level_displayed: usize, stars: usize, last: bool) {
+
+    if 0 == level_displayed { return; }
+
+    let k = level_displayed - 1;
+    if data.completed.len() <= k {
+        data.completed.resize(level_displayed, false);
+        data.stars.resize(level_displayed, 0);
+    }
+    data.completed[k] = true;
+    data.stars[k] = data.stars[k].max(stars);
+
+    if last {
+        data.level = 0;
+    }
+}
+
+
+pub fn is_completed(data: &Progress, level_displayed: usize) -> bool {
+    0 < level_displayed && data.completed.get(level_displayed - 1).copied().unwrap_or(false)
+}
+
+
+pub fn num_completed(data: &Progress) -> usize {
+    data.completed.iter().filter(|c| **c).count()
+}
+
+
+// continue,<level>,<sub level>,<option>, then level,<level>,<stars> per completed level.
+// a missing file just means a fresh campaign
+pub fn load_progress(filename: &str) -> Progress {
+
+    let mut data = Progress::default();
+
+    let file = match File::open(filename) {
+        Ok(file) => file,
+        Err(_) => return data,
+    };
+
+    let reader = io::BufReader::new(file);
+    for line in reader.lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(_) => break,
+        };
+        let split: Vec<&str> = line.trim().split(',').collect();
+        let field = |i: usize| split.get(i).and_then(|v| v.parse::<usize>().ok());
+
+        match split[0] {
+            "continue" => {
+                if let (Some(level), Some(sub_level), Some(level_option)) = (field(1), field(2), field(3)) {
+                    resume_at(&mut data, level, sub_level, level_option);
+                }
+            },
+            "level" => {
+                if let (Some(level), Some(stars)) = (field(1), field(2)) {
+                    complete(&mut data, level, stars, false);
+                }
+            },
+            _ => (),
+        }
+    }
+
+    data
+}
+
+
+pub fn save_progress(filename: &str, data: &Progress) -> io::Result<()> {
+
+    let mut file = File::create(filename)?;
+
+    writeln!(file, "continue,{},{},{}", data.level, data.sub_level, data.level_option)?;
+    for k in 0..data.completed.len() {
+        if data.completed[k] {
+            writeln!(file, "level,{},{}", k + 1, data.stars[k])?;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,125 @@
+use std::fs::File;
+use std::io::{self, BufRead, Write};
+
+use super::enums::*;
+use super::game_state::PieceData;
+use super::history::{self, UndoTree};
+
+pub const BOARD_FILE: &str = "board.dat";
+
+// an unfinished board as it was left on quit, undo history included
+#[derive(Default)]
+pub struct SavedBoard {
+    pub level: usize,
+    pub sub_level: usize,
+    pub level_option: usize,
+    pub seed: u64,
+    pub daily: u32,
+    pub final_level: bool,
+    pub moves: usize,
+    pub hints: usize,
+    pub orient: u8,
+    pub tilemap: Vec<u16>, // flips and rotations included
+    pub pieces: Vec<PieceData>,
+    pub history: UndoTree, // read back by load_board, save_board writes the live tree
+    pub history_idx: usize,
+}
+
+
+// board,<level>,<sub level>,<option>,<seed>,<daily>,<final>,<moves>,<hints>,<orient>
+// tilemap,<tiles..>
+// piece,<tile>,<board idx>,<locked>, one per piece
+// history,<history node>,<redo root>
+// node,<parent>,<redo>,<command>, one per undo tree node, see history::encode. - is the root
+pub fn save_board(filename: &str, data: &SavedBoard, history: &UndoTree) -> io::Result<()> {
+
+    let mut file = File::create(filename)?;
+
+    writeln!(file, "board,{},{},{},{},{},{},{},{},{}", data.level, data.sub_level, data.level_option, data.seed, data.daily, data.final_level as u8, data.moves, data.hints, data.orient)?;
+    writeln!(file, "tilemap,{}", join(&data.tilemap))?;
+    for p in &data.pieces {
+        writeln!(file, "piece,{},{},{}", p.tile, p.board_idx, p.locked as u8)?;
+    }
+    writeln!(file, "history,{},{}", history::node_text(data.history_idx), history::node_text(history.redo_root))?;
+    for n in &history.nodes {
+        writeln!(file, "node,{},{},{}", history::node_text(n.parent), history::node_text(n.redo), history::encode(&n.cmd))?;
+    }
+
+    Ok(())
+}
+
+
+// None when there is no saved board or it does not read back in full
+pub fn load_board(filename: &str) -> Option<SavedBoard> {
+
+    let file = File::open(filename).ok()?;
+
+    let mut data = SavedBoard::default();
+    let mut has_board = false;
+
+    let reader = io::BufReader::new(file);
+    for line in reader.lines() {
+        let line = line.ok()?;
+        let (key, rest) = line.trim().split_once(',')?;
+
+        match key {
+            "board" => {
+                let f: Vec<u64> = parse_list(rest)?;
+                if 9 != f.len() || 8 <= f[8] { return None; }
+                data.level = f[0] as usize;
+                data.sub_level = f[1] as usize;
+                data.level_option = f[2] as usize;
+                data.seed = f[3];
+                data.daily = f[4] as u32;
+                data.final_level = 0 != f[5];
+                data.moves = f[6] as usize;
+                data.hints = f[7] as usize;
+                data.orient = f[8] as u8;
+                has_board = true;
+            },
+            "tilemap" => data.tilemap = parse_list(rest)?,
+            "piece" => {
+                let f: Vec<u16> = parse_list(rest)?;
+                if 3 != f.len() || MAX_PIECES == data.pieces.len() { return None; }
+                if !(REFLECTOR_TL..=REFLECTOR_R).contains(&f[0]) { return None; }
+                data.pieces.push(PieceData {
+                    tile: f[0],
+                    board_idx: f[1],
+                    active: false,
+                    home: data.pieces.len() as u8,
+                    locked: 0 != f[2],
+                });
+            },
+            "history" => {
+                let (idx, redo) = rest.split_once(',')?;
+                data.history_idx = history::parse_node(idx)?;
+                data.history.redo_root = history::parse_node(redo)?;
+            },
+            "node" => {
+                let split: Vec<&str> = rest.splitn(3, ',').collect();
+                if 3 != split.len() { return None; }
+                let n = history::add_node(&mut data.history, history::parse_node(split[0])?, history::parse(split[2])?);
+                data.history.nodes[n].redo = history::parse_node(split[1])?;
+            },
+            _ => (),
+        }
+    }
+
+    if !has_board || !history::is_valid(&data.history, data.history_idx) { return None; }
+
+    Some(data)
+}
+
+
+pub fn delete_board(filename: &str) {
+    let _ = std::fs::remove_file(filename);
+}
+
+
+fn join(values: &[u16]) -> String {
+    values.iter().map(|v| v.to_string()).collect::<Vec<String>>().join(",")
+}
+
+fn parse_list<T: std::str::FromStr>(text: &str) -> Option<Vec<T>> {
+    text.split(',').map(|v| v.parse::<T>().ok()).collect()
+}
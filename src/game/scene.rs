@@ -2,28 +2,23 @@ use crate::mgfw;
 use crate::mgfw::ecs;
 use crate::mgfw::log;
 
+use super::beam;
 use super::game;
 use super::enums::*;
 use super::game::GameData;
 use super::game::GameDataHeap;
-use super::game_state::Block;
+use super::game_state;
+use super::levels;
+use super::levels::Block;
 
 use rand;
 use rand::prelude::*;
+use rand::rngs::StdRng;
 
 
 pub type TileMap = Vec<u16>;
 pub type ClippingMap = Vec<u8>;
 
-#[derive(Default, Clone, Copy)]
-pub struct PieceData {
-    pub tile: u16,
-    pub board_idx: u8,
-    pub active: bool,
-    pub home: u8,
-    pub available: bool,
-}
-
 // heap data
 #[derive(Default, Clone)]
 pub struct SceneData {
@@ -31,6 +26,7 @@ pub struct SceneData {
     pub clipping: ClippingMap,
     pub reflectors: TileMap,
     pub beams: TileMap,
+    pub portals: Vec<(usize, usize)>, // linked board idx pairs
     pub sz: usize,
     pub sz2: usize,
     pub num_goals: u8,
@@ -46,6 +42,7 @@ impl SceneData {
             clipping: vec![0; sz2],
             reflectors: vec![0; sz2],
             beams: vec![0; sz2],
+            portals: Vec::new(),
             sz,
             sz2,
             num_goals: 0,
@@ -59,38 +56,33 @@ impl SceneData {
             clipping: Vec::new(),
             reflectors: Vec::new(),
             beams: Vec::new(),
+            portals: Vec::new(),
             sz: 1,
             sz2: 1,
-            //pieces: SceneData::init_pieces(),
             num_goals: 0,
             num_pcs: 0,
         }
     }
+}
 
-    /*fn init_pieces() -> [PieceData; MAX_PIECES] {
-        let pcs = vec![17, 18, 23, 22, 19, 20, 24, 21, 17, 18, 23, 22, 19, 20, 24, 21, 17, 18, 23, 22, 19, 20, 24, 21];
-        assert!(MAX_PIECES == pcs.len());
 
-        let mut data = [PieceData::default(); MAX_PIECES];
-        for i in 0..MAX_PIECES {
-            data[i].tile = pcs[i];
-            data[i].board_idx = BOARD_IDX_INVALID;
-            data[i].home = i as u8;
-            data[i].active = false;
-            data[i].available = true;
-        }
+// lay out a block with the 30% block scatter drawn from the given generator,
+// the same seed gives the same board
+pub fn build_scene(block: &Block, rng: &mut impl Rng) -> SceneData {
+    build_scene_impl(block, Some(rng))
+}
+
 
-        data
-    }*/
+// same layout as build_scene but without the random block scatter
+pub fn build_scene_empty(block: &Block) -> SceneData {
+    build_scene_impl::<StdRng>(block, None)
 }
 
 
-pub fn build_scene(block: &Block) -> SceneData {
+fn build_scene_impl<R: Rng>(block: &Block, mut scatter: Option<&mut R>) -> SceneData {
 
     let mut scene = SceneData::new(block.sz);
 
-    let mut rng: ThreadRng = rand::thread_rng();
-
     let sz = scene.sz;
     let sz2 = scene.sz2;
 
@@ -98,14 +90,16 @@ pub fn build_scene(block: &Block) -> SceneData {
     for y in 1..sz-1 {
         for x in 1..sz-1 {
             let idx = y * sz + x;
-            scene.tilemap[idx] = FLOOR + ((y + x) % 2) as u16;
+            scene.tilemap[idx] = if 0 == (y + x) % 2 { FLOOR } else { FLOOR_ALT };
         }
     }
 
     // set blocks
     for i in 0..sz2 {
-        if 46 == block.map[i] && rng.gen::<f32>() < 0.3 {
-            scene.tilemap[i] = BLOCK;
+        if let Some(rng) = scatter.as_mut() {
+            if CELL_FLOOR == block.map[i] && rng.gen::<f32>() < 0.3 {
+                scene.tilemap[i] = BLOCK;
+            }
         }
     }
     
@@ -123,13 +117,26 @@ pub fn build_scene(block: &Block) -> SceneData {
         scene.tilemap[(sz - 1) * sz + i] = SOURCE_U;
     }
 
-    // set goals
+    // set goals, filters and source colors
     for i in 0..sz2 {
-        if 111 == block.map[i] {
-            scene.tilemap[i] = ORB;
+        let cell = block.map[i];
+        let tint = COLOR_TILE_OFFSET * levels::cell_color(cell) as u16;
+
+        if beam::is_source(scene.tilemap[i]) {
+            scene.tilemap[i] += tint;
+
+        } else if levels::is_orb_cell(cell) {
+            scene.tilemap[i] = if 0 == tint { ORB } else { ORB_TINTED + tint };
             scene.num_goals += 1;
+
+        } else if levels::is_filter_cell(cell) {
+            scene.tilemap[i] = FILTER + tint;
+
+        } else if levels::is_portal_cell(cell) {
+            scene.tilemap[i] = PORTAL + (cell - b'1') as u16;
         }
     }
+    link_portals(&mut scene);
 
     
     // base clipping
@@ -137,11 +144,11 @@ pub fn build_scene(block: &Block) -> SceneData {
 
     for i in 0..sz2 {
         let tile = scene.tilemap[i];
-        if SOURCE_U >= tile && SOURCE_R <= tile {
+        if beam::is_source(tile) {
             clip[i] = CLIPPING_SOURCE;
         }
         if 0 == tile || BLOCK == tile { clip[i] = BLOCK as u8; }
-        if ORB == tile { clip[i] = ORB as u8; }
+        if beam::is_orb(tile) || beam::is_filter(tile) || beam::is_portal(tile) { clip[i] = tile as u8; }
     }
 
     scene.clipping = clip;
@@ -151,38 +158,134 @@ pub fn build_scene(block: &Block) -> SceneData {
 
 }
 
+// a level's map laid out on a board turned to orient, mirrored first when
+// 4 <= orient like PlayerData::orient. same cell moves as flip_h and rotate
+pub fn oriented_map(block: &Block, orient: u8) -> Vec<u8> {
+
+    let sz = block.sz;
+    let mut map = vec![0; sz * sz];
+    for yy in 0..sz {
+        for xx in 0..sz {
+            let (mut x1, mut y1) = (xx, yy);
+            if 4 <= orient { x1 = sz - 1 - x1; }
+            for _ in 0..orient % 4 {
+                let x0 = x1;
+                x1 = sz - 1 - y1;
+                y1 = x0;
+            }
+            map[y1 * sz + x1] = block.map[yy * sz + xx];
+        }
+    }
+    map
+}
+
+
+// open floor a piece can go on, either tile of the checkerboard
+pub fn is_floor(tile: u16) -> bool {
+    FLOOR == tile || FLOOR_ALT == tile
+}
+
+
+// pair up the portal tiles on the board, the tilemap is what flips,
+// rotations and undo move around so the pairs are always read back from it
+pub fn link_portals(scene: &mut SceneData) {
+
+    scene.portals.clear();
+
+    for k in 0..NUM_PORTAL_PAIRS as u16 {
+        let mut ends: Vec<usize> = Vec::new();
+        for i in 0..scene.sz2 {
+            if PORTAL + k == scene.tilemap[i] {
+                ends.push(i);
+            }
+        }
+        if 2 == ends.len() {
+            scene.portals.push((ends[0], ends[1]));
+        }
+    }
+}
+
+// the other end of the portal at idx
+pub fn portal_pair(scene: &SceneData, idx: usize) -> Option<usize> {
+    for &(a, b) in &scene.portals {
+        if a == idx { return Some(b); }
+        if b == idx { return Some(a); }
+    }
+    None
+}
+
+
 pub fn update_clipping(cache: &mut GameData, heap: &mut GameDataHeap) {
 
-    let sz2 = heap.scene_data.sz2;
     let npcs = heap.scene_data.num_pcs as usize;
+    heap.scene_data.clipping = build_clipping(&heap.scene_data, &cache.pieces[0..npcs]);
+
+}
+
+
+pub fn build_clipping(scene: &SceneData, pieces: &[game_state::PieceData]) -> ClippingMap {
+
+    let sz2 = scene.sz2;
 
     let mut clip = vec![CLIPPING_NONE; sz2];
 
     for i in 0..sz2 {
-        let tile = heap.scene_data.tilemap[i];
-        if SOURCE_U >= tile && SOURCE_R <= tile {
+        let tile = scene.tilemap[i];
+        if beam::is_source(tile) {
             clip[i] = CLIPPING_SOURCE;
         }
         if BLOCK == tile { clip[i] = tile as u8; }
-        if ORB == tile { clip[i] = tile as u8; }
+        if beam::is_orb(tile) || beam::is_filter(tile) || beam::is_portal(tile) { clip[i] = tile as u8; }
     }
 
-    for i in 0..npcs {
-        if BOARD_IDX_INVALID != cache.pieces[i].board_idx {
-            clip[cache.pieces[i].board_idx as usize] = cache.pieces[i].tile as u8;
+    for i in 0..pieces.len() {
+        if BOARD_IDX_INVALID != pieces[i].board_idx {
+            clip[pieces[i].board_idx as usize] = pieces[i].tile as u8;
         }
     }
 
-    heap.scene_data.clipping = clip;
+    clip
 
 }
 
 
 
+// give the pieces the level's inventory and put the ones it locks on the
+// board. locks the inventory has no piece left for are dropped
+pub fn lock_pieces(block: &Block, pieces: &mut [game_state::PieceData]) {
+
+    for (p, tile) in pieces.iter_mut().zip(block.pieces.iter()) {
+        p.tile = *tile;
+        p.board_idx = BOARD_IDX_INVALID;
+        p.locked = false;
+    }
+
+    for (idx, tile) in levels::locked_pieces(block) {
+        if let Some(p) = pieces.iter_mut().find(|p| tile == p.tile && !p.locked) {
+            p.board_idx = idx as u16;
+            p.locked = true;
+        }
+    }
+}
+
+
+// inventory grid tiles, padded out to whole rows of 4
+pub fn inventory_tiles(pieces: &[game_state::PieceData]) -> Vec<u16> {
+    let mut tiles = vec![0; pieces.len().div_ceil(4) * 4];
+    for i in 0..pieces.len() {
+        tiles[i] = pieces[i].tile;
+    }
+    tiles
+}
+
+
+// every piece back to the inventory, except the locked ones
 pub fn reset_scene(cache: &mut GameData, heap: &mut GameDataHeap) {
     let npcs = heap.scene_data.num_pcs as usize;
     for i in 0..npcs {
-        cache.pieces[i].board_idx = BOARD_IDX_INVALID;
+        if !cache.pieces[i].locked {
+            cache.pieces[i].board_idx = BOARD_IDX_INVALID;
+        }
     }
 }
 
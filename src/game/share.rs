@@ -0,0 +1,80 @@
+use super::enums::*;
+use super::game_state::PieceData;
+use super::levels::{self, Block};
+
+pub const SHARE_FILE: &str = "share.txt";
+
+// a board passed around as text, the cells are as the board is shown now
+pub struct SharedBoard {
+    pub block: Block,
+    pub orient: u8, // see PlayerData
+    pub blocked: Vec<bool>, // block scatter
+    pub placements: Vec<(usize, u16)>, // board idx and tile of each placed piece, empty for a bare puzzle
+}
+
+
+// <orient>,<scatter>,<placements>,<levels.dat line>. scatter is an 'x' for each
+// scattered block and placements the reflector cells of the pieces put down,
+// both in the levels.dat map alphabet. placements is left empty for a bare puzzle
+pub fn encode(block: &Block, orient: u8, tilemap: &[u16], pieces: &[PieceData], with_placements: bool) -> String {
+
+    let scatter: Vec<u8> = tilemap.iter().map(|t| if BLOCK == *t { b'x' } else { b'.' }).collect();
+
+    let mut placed = String::new();
+    if with_placements {
+        let mut map = vec![b'.'; tilemap.len()];
+        for p in pieces {
+            if BOARD_IDX_INVALID != p.board_idx && !p.locked {
+                map[p.board_idx as usize] = levels::piece_cell(p.tile);
+            }
+        }
+        placed = levels::encode_cells(&map);
+    }
+
+    format!("{orient},{},{placed},{}", levels::encode_cells(&scatter), levels::encode_block(block))
+}
+
+
+pub fn decode(code: &str) -> Result<SharedBoard, String> {
+
+    let split: Vec<&str> = code.trim().splitn(4, ',').collect();
+    if 4 != split.len() {
+        return Err(String::from("expected orient,scatter,placements,level"));
+    }
+
+    let orient = match split[0].parse::<u8>() {
+        Ok(orient) if 8 > orient => orient,
+        _ => return Err(format!("bad orientation '{}'", split[0])),
+    };
+
+    let block = levels::parse_block(split[3])?;
+    let sz2 = block.sz * block.sz;
+
+    let scatter = levels::decode_cells(split[1])?;
+    if sz2 != scatter.len() {
+        return Err(format!("scatter has {} cells, expected {sz2}", scatter.len()));
+    }
+    let blocked = scatter.iter().map(|c| b'x' == *c).collect();
+
+    let mut placements = Vec::new();
+    if !split[2].is_empty() {
+        let map = levels::decode_cells(split[2])?;
+        if sz2 != map.len() {
+            return Err(format!("placements have {} cells, expected {sz2}", map.len()));
+        }
+        for (i, cell) in map.iter().enumerate() {
+            if b'.' == *cell { continue; }
+            match levels::cell_piece(*cell) {
+                REFLECTOR_INVALID => return Err(format!("bad placement '{}'", char::from(*cell))),
+                tile => placements.push((i, tile)),
+            }
+        }
+    }
+
+    Ok(SharedBoard {
+        block,
+        orient,
+        blocked,
+        placements,
+    })
+}
@@ -0,0 +1,725 @@
+use super::beam;
+use super::beam::{opposite, reflector_arms, step};
+use super::enums::*;
+use super::game_state::PieceData;
+use super::levels;
+use super::levels::Block;
+use super::scene;
+use super::scene::SceneData;
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+pub const NUM_REFLECTORS: usize = 8;
+
+// node budgets for a hint, first building on the player's pieces then from scratch
+pub const HINT_KEEP_NODES: usize = 250_000;
+pub const HINT_MAX_NODES: usize = 2_000_000;
+
+// moves a par counts on top of placing the pieces, the one firing the beam
+pub const PAR_FIRE_MOVES: usize = 1;
+
+// number of pieces of each reflector type, indexed by tile - REFLECTOR_TL
+pub type Inventory = [u8; NUM_REFLECTORS];
+
+#[derive(Clone)]
+pub struct Solution {
+    pub source: usize,
+    pub placements: Vec<(usize, u16)>, // (board idx, reflector tile)
+}
+
+#[derive(Default, Clone)]
+pub struct SolverStats {
+    pub nodes: usize,
+    pub max_depth: usize,
+    pub micros: u128,
+    pub complete: bool, // false if the node limit cut the search short
+}
+
+pub struct SolverResult {
+    pub first: Option<Solution>,
+    pub count: usize,
+    pub stats: SolverStats,
+}
+
+pub struct SolverConfig {
+    pub max_nodes: usize,
+    pub max_solutions: usize,
+    // only try pieces the beam lights that can still lead to an unlit orb.
+    // much faster at finding a solution, but skips some so counts are off
+    pub greedy: bool,
+    pub cancel: Option<Arc<AtomicBool>>,
+}
+
+impl SolverConfig {
+    pub fn new() -> SolverConfig {
+        SolverConfig {
+            max_nodes: 1_000_000,
+            max_solutions: usize::MAX,
+            greedy: false,
+            cancel: None,
+        }
+    }
+}
+
+
+// one step toward a solution: move pieces[piece] to board_idx.
+// a board_idx of BOARD_IDX_INVALID sends the piece back to the inventory,
+// a piece of HOLDING_INVALID means everything is in place and board_idx
+// is the source to fire the beam from.
+#[derive(Clone, Copy)]
+pub struct Hint {
+    pub piece: u8,
+    pub board_idx: u16,
+}
+
+// solver search running on a worker thread so the game keeps drawing
+pub struct SolverJob<T> {
+    handle: thread::JoinHandle<Option<T>>,
+    cancel: Arc<AtomicBool>,
+}
+
+pub type HintJob = SolverJob<Hint>;
+pub type ParJob = SolverJob<usize>;
+
+impl<T: Send + 'static> SolverJob<T> {
+    fn run(work: impl FnOnce(Arc<AtomicBool>) -> Option<T> + Send + 'static) -> SolverJob<T> {
+        let cancel = Arc::new(AtomicBool::new(false));
+        let flag = cancel.clone();
+
+        SolverJob {
+            handle: thread::spawn(move || work(flag)),
+            cancel,
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.handle.is_finished()
+    }
+
+    pub fn join(self) -> Option<T> {
+        self.handle.join().unwrap_or(None)
+    }
+
+    // the thread notices on its next node and winds down on its own
+    pub fn cancel(self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+}
+
+impl HintJob {
+    pub fn spawn(block: &Block, scene: &SceneData, orient: u8, pieces: &[PieceData]) -> HintJob {
+        let block = block.clone();
+        let scene = scene.clone();
+        let pieces = pieces.to_vec();
+        SolverJob::run(move |flag| hint(&block, &scene, orient, &pieces, Some(flag)))
+    }
+}
+
+impl ParJob {
+    pub fn spawn(block: &Block) -> ParJob {
+        let block = block.clone();
+        SolverJob::run(move |flag| par(&block, Some(flag)))
+    }
+}
+
+
+// how many of each reflector a piece list holds
+pub fn inventory(pieces: &[u16]) -> Inventory {
+    let mut inv = [0; NUM_REFLECTORS];
+    for &tile in pieces {
+        inv[(tile - REFLECTOR_TL) as usize] += 1;
+    }
+    inv
+}
+
+
+// solve a level block on its bare layout, without the random block scatter
+pub fn solve(block: &Block, config: &SolverConfig) -> SolverResult {
+    let scene = scene::build_scene_empty(block);
+    let (avail, fixed) = locked_pieces(block);
+    solve_scene(&scene, &avail, &fixed, config)
+}
+
+
+// moves for a level's par, one per piece a solution places plus firing the
+// beam. the level's own par if it has one, otherwise the first solution
+// along the designed path, which may not be the shortest
+pub fn par(block: &Block, cancel: Option<Arc<AtomicBool>>) -> Option<usize> {
+
+    if 0 != block.par { return Some(block.par); }
+
+    let sol = path_solve(block, HINT_MAX_NODES, cancel).first?;
+
+    Some(sol.placements.len() + PAR_FIRE_MOVES)
+}
+
+
+// greedy search along the designed path on the bare layout. quick to find a
+// first solution, the count is no use
+fn path_solve(block: &Block, max_nodes: usize, cancel: Option<Arc<AtomicBool>>) -> SolverResult {
+
+    let config = SolverConfig {
+        max_nodes,
+        max_solutions: 1,
+        greedy: true,
+        cancel,
+    };
+
+    let scene = scene::build_scene_empty(block);
+    let (avail, fixed) = locked_pieces(block);
+    let path = path_scene(block, &scene, 0, &fixed);
+    solve_scene(&path, &avail, &fixed, &config)
+}
+
+
+// what is left of the inventory around the pieces the level locks in place,
+// and those pieces. same order and limits as scene::lock_pieces
+fn locked_pieces(block: &Block) -> (Inventory, Vec<PieceData>) {
+
+    let mut avail = inventory(&block.pieces);
+    let mut fixed = Vec::new();
+    for (idx, tile) in levels::locked_pieces(block) {
+        let k = (tile - REFLECTOR_TL) as usize;
+        if 0 == avail[k] { continue; }
+
+        avail[k] -= 1;
+        fixed.push(PieceData { tile, board_idx: idx as u16, active: false, home: 0, locked: true });
+    }
+
+    (avail, fixed)
+}
+
+
+// search every edge source for piece placements that light all orbs.
+// fixed pieces stay where they are, the rest of the inventory may be
+// placed on floor cells. a solution is recorded as soon as all orbs are
+// lit, and every piece the search placed has to be hit by the beam.
+pub fn solve_scene(scene: &SceneData, avail: &Inventory, fixed: &[PieceData], config: &SolverConfig) -> SolverResult {
+
+    let start = std::time::Instant::now();
+
+    let mut search = Search {
+        scene,
+        config,
+        pieces: fixed.to_vec(),
+        nfixed: fixed.len(),
+        decided: vec![false; scene.sz2],
+        avail: *avail,
+        source: 0,
+        source_nodes: 0,
+        source_limit: usize::MAX,
+        result: SolverResult {
+            first: None,
+            count: 0,
+            stats: SolverStats::default(),
+        },
+        aborted: false,
+    };
+
+    for p in fixed {
+        if BOARD_IDX_INVALID != p.board_idx {
+            search.decided[p.board_idx as usize] = true;
+        }
+    }
+
+    let sources = beam::sources(scene);
+
+    if !config.greedy {
+        for &source in &sources {
+            search.source = source;
+            search.search(0, false);
+            if search.aborted { break; }
+        }
+
+    } else {
+        // a hopeless source can eat the whole budget, so every source gets a
+        // slice of nodes in turn and the slice doubles each round
+        let mut done = vec![false; sources.len()];
+        let mut slice = 500;
+        while !search.aborted && done.contains(&false) {
+            for k in 0..sources.len() {
+                if done[k] { continue; }
+                search.source = sources[k];
+                search.source_nodes = 0;
+                search.source_limit = slice;
+                search.search(0, false);
+                if search.aborted { break; }
+                if search.source_nodes <= slice { done[k] = true; }
+            }
+            slice *= 2;
+        }
+    }
+
+    search.result.stats.complete = !search.aborted;
+    search.result.stats.micros = std::time::Instant::now().duration_since(start).as_micros();
+    search.result
+}
+
+
+// find a hint for the board as the player sees it, with any flips and
+// rotations applied as orient says, see PlayerData::orient. pieces the
+// player placed are kept if the level can still be solved around them,
+// otherwise a misplaced piece gets corrected.
+pub fn hint(block: &Block, scene: &SceneData, orient: u8, pieces: &[PieceData], cancel: Option<Arc<AtomicBool>>) -> Option<Hint> {
+
+    let mut config = SolverConfig {
+        max_nodes: HINT_KEEP_NODES,
+        max_solutions: 1,
+        greedy: true,
+        cancel,
+    };
+
+    let mut fixed = Vec::new();
+    let mut avail = [0; NUM_REFLECTORS];
+    for i in 0..pieces.len() {
+        if BOARD_IDX_INVALID == pieces[i].board_idx {
+            avail[(pieces[i].tile - REFLECTOR_TL) as usize] += 1;
+        } else {
+            fixed.push(pieces[i]);
+        }
+    }
+
+    // first try to build on what the player has so far
+    if !fixed.is_empty() {
+        let path = path_scene(block, scene, orient, &fixed);
+        if let Some(sol) = solve_scene(&path, &avail, &fixed, &config).first {
+            if sol.placements.is_empty() {
+                return Some(Hint { piece: HOLDING_INVALID, board_idx: sol.source as u16 });
+            }
+            return from_inventory(pieces, sol.placements[0]);
+        }
+    }
+
+    // otherwise solve from scratch around the locked pieces and fix a piece that doesn't belong
+    config.max_nodes = HINT_MAX_NODES;
+    let mut locked = Vec::new();
+    let mut avail = [0; NUM_REFLECTORS];
+    for p in pieces {
+        if p.locked {
+            locked.push(*p);
+        } else {
+            avail[(p.tile - REFLECTOR_TL) as usize] += 1;
+        }
+    }
+    let path = path_scene(block, scene, orient, &locked);
+    let sol = solve_scene(&path, &avail, &locked, &config).first?;
+
+    let mut needed = sol.placements.clone();
+    let mut wrong = Vec::new();
+    for (i, piece) in pieces.iter().enumerate() {
+        if BOARD_IDX_INVALID == piece.board_idx || piece.locked { continue; }
+
+        let place = (piece.board_idx as usize, piece.tile);
+        match needed.iter().position(|p| *p == place) {
+            Some(n) => { needed.remove(n); },
+            None => wrong.push(i),
+        }
+    }
+
+    // move a misplaced piece to where its type is needed, or take it off the board
+    for &i in &wrong {
+        if let Some(p) = needed.iter().find(|p| p.1 == pieces[i].tile) {
+            return Some(Hint { piece: i as u8, board_idx: p.0 as u16 });
+        }
+    }
+    if let Some(&i) = wrong.first() {
+        return Some(Hint { piece: i as u8, board_idx: BOARD_IDX_INVALID });
+    }
+
+    // nothing misplaced, building on the player's pieces just ran out of nodes
+    match needed.first() {
+        Some(&p) => from_inventory(pieces, p),
+        None => Some(Hint { piece: HOLDING_INVALID, board_idx: sol.source as u16 }),
+    }
+}
+
+
+// hint to take a piece of the given type out of the inventory
+fn from_inventory(pieces: &[PieceData], place: (usize, u16)) -> Option<Hint> {
+    let i = pieces.iter().position(|p| BOARD_IDX_INVALID == p.board_idx && place.1 == p.tile)?;
+    Some(Hint { piece: i as u8, board_idx: place.0 as u16 })
+}
+
+
+// copy of the scene with every cell off the designed beam path blocked, so the
+// search only has to consider the cells the level was built around. the block
+// map is laid out on the board turned to orient, cells under the given pieces
+// stay open. lighting only grows with fewer blocks, so anything solved here
+// also solves the real board.
+fn path_scene(block: &Block, scene: &SceneData, orient: u8, pieces: &[PieceData]) -> SceneData {
+
+    let sz = scene.sz;
+    let mut path = scene.clone();
+
+    let map = scene::oriented_map(block, orient);
+    for yy in 1..sz-1 {
+        for xx in 1..sz-1 {
+            if CELL_FLOOR == map[yy * sz + xx] {
+                path.tilemap[yy * sz + xx] = BLOCK;
+            }
+        }
+    }
+    for p in pieces {
+        if BOARD_IDX_INVALID != p.board_idx {
+            let idx = p.board_idx as usize;
+            path.tilemap[idx] = scene.tilemap[idx];
+        }
+    }
+
+    path
+}
+
+
+struct Search<'a> {
+    scene: &'a SceneData,
+    config: &'a SolverConfig,
+    pieces: Vec<PieceData>,
+    nfixed: usize,
+    decided: Vec<bool>,
+    avail: Inventory,
+    source: usize,
+    source_nodes: usize,
+    source_limit: usize,
+    result: SolverResult,
+    aborted: bool,
+}
+
+impl<'a> Search<'a> {
+
+    fn search(&mut self, depth: usize, placed: bool) {
+
+        if self.aborted { return; }
+
+        self.result.stats.nodes += 1;
+        if self.result.stats.max_depth < depth {
+            self.result.stats.max_depth = depth;
+        }
+        if self.config.max_nodes < self.result.stats.nodes {
+            self.aborted = true;
+            return;
+        }
+        if let Some(cancel) = &self.config.cancel {
+            if cancel.load(Ordering::Relaxed) {
+                self.aborted = true;
+                return;
+            }
+        }
+
+        self.source_nodes += 1;
+        if self.source_limit < self.source_nodes { return; }
+
+        let trace = beam::trace(self.scene, &self.pieces, self.source);
+
+        // a piece that doesn't light up where it was put only blocks the beam
+        if self.config.greedy && placed {
+            let last = self.pieces[self.pieces.len() - 1].board_idx as usize;
+            if 0 == trace.beams[last] { return; }
+        }
+
+        if trace.lit_orbs.len() == self.scene.num_goals as usize {
+            // every piece the search placed must be part of the beam
+            for i in self.nfixed..self.pieces.len() {
+                if 0 == trace.beams[self.pieces[i].board_idx as usize] { return; }
+            }
+
+            if self.result.first.is_none() {
+                let mut placements = Vec::new();
+                for i in self.nfixed..self.pieces.len() {
+                    placements.push((self.pieces[i].board_idx as usize, self.pieces[i].tile));
+                }
+                self.result.first = Some(Solution { source: self.source, placements });
+            }
+
+            self.result.count += 1;
+            if self.config.max_solutions <= self.result.count {
+                self.aborted = true;
+            }
+            return;
+        }
+
+        // nothing left to place, the beam can't change anymore
+        let mut remaining = 0;
+        for k in 0..NUM_REFLECTORS {
+            remaining += self.avail[k] as usize;
+        }
+        if 0 == remaining { return; }
+
+        if !self.orbs_reachable(&trace) { return; }
+
+        // branch on the first lit floor cell nobody has decided on yet
+        let mut cell = BOARD_IDX_INVALID as usize;
+        for i in 0..trace.path.len() {
+            let idx = trace.path[i].0;
+            let tile = self.scene.tilemap[idx];
+            if !self.decided[idx] && scene::is_floor(tile) {
+                cell = idx;
+                break;
+            }
+        }
+        if BOARD_IDX_INVALID as usize == cell { return; }
+
+        self.decided[cell] = true;
+
+        // leave the cell empty
+        self.search(depth + 1, false);
+
+        // or put any available reflector there
+        for k in 0..NUM_REFLECTORS {
+            if 0 == self.avail[k] { continue; }
+
+            self.avail[k] -= 1;
+            self.pieces.push(PieceData {
+                tile: REFLECTOR_TL + k as u16,
+                board_idx: cell as u16,
+                active: false,
+                home: 0,
+                locked: false,
+            });
+
+            if !self.config.greedy || self.useful(&trace, cell) {
+                self.search(depth + 1, true);
+            }
+
+            self.pieces.pop();
+            self.avail[k] += 1;
+        }
+
+        self.decided[cell] = false;
+    }
+
+    // can every unlit orb still be hit by re-routing the beam at undecided lit cells?
+    fn orbs_reachable(&self, trace: &beam::BeamTrace) -> bool {
+
+        let mut seeds = Vec::new();
+        for i in 0..self.scene.sz2 {
+            let tile = self.scene.tilemap[i];
+            if !self.decided[i] && 0 != trace.beams[i] && scene::is_floor(tile) {
+                for dir in 0..4 {
+                    if let Some(next) = step(i, dir, self.scene.sz) {
+                        seeds.push((next, dir));
+                    }
+                }
+            }
+        }
+
+        self.reachable(trace, seeds, true)
+    }
+
+    // can the piece just placed at cell send its beam toward any unlit orb?
+    fn useful(&self, trace: &beam::BeamTrace, cell: usize) -> bool {
+
+        let (_, emit) = reflector_arms(self.pieces[self.pieces.len() - 1].tile);
+
+        let mut seeds = Vec::new();
+        for (dir, &arm) in emit.iter().enumerate() {
+            if !arm { continue; }
+            if let Some(next) = step(cell, dir, self.scene.sz) {
+                seeds.push((next, dir));
+            }
+        }
+
+        self.reachable(trace, seeds, false)
+    }
+
+    // walk beams out from the seeds: decided floor passes them straight through,
+    // placed pieces follow their reflector rules and undecided floor may turn them
+    // any way. checks that all unlit orbs are hit, or just one of them.
+    fn reachable(&self, trace: &beam::BeamTrace, mut stack: Vec<(usize, usize)>, all: bool) -> bool {
+
+        let sz = self.scene.sz;
+        let sz2 = self.scene.sz2;
+
+        let mut unlit = 0;
+        for i in 0..sz2 {
+            if beam::is_orb(self.scene.tilemap[i]) && 0 == trace.beams[i] {
+                unlit += 1;
+            }
+        }
+        if 0 == unlit { return all; }
+        if !all { unlit = 1; }
+
+        let mut content = vec![0; sz2];
+        for i in 0..self.pieces.len() {
+            if BOARD_IDX_INVALID != self.pieces[i].board_idx {
+                content[self.pieces[i].board_idx as usize] = self.pieces[i].tile;
+            }
+        }
+
+        // (cell, travel direction) states
+        let mut visited = vec![false; sz2 * 4];
+        let mut reached = vec![false; sz2];
+
+        while let Some((idx, dir)) = stack.pop() {
+            if visited[idx * 4 + dir] { continue; }
+            visited[idx * 4 + dir] = true;
+
+            let x = idx % sz;
+            let y = idx / sz;
+            if 0 == x || 0 == y || sz - 1 == x || sz - 1 == y { continue; }
+
+            let tile = self.scene.tilemap[idx];
+            if beam::is_orb(tile) {
+                if !reached[idx] && 0 == trace.beams[idx] {
+                    reached[idx] = true;
+                    unlit -= 1;
+                    if 0 == unlit { return true; }
+                }
+                continue;
+            }
+            if BLOCK == tile { continue; }
+
+            // out of the other end, still going the same way
+            if beam::is_portal(tile) {
+                if let Some(next) = scene::portal_pair(self.scene, idx).and_then(|p| step(p, dir, sz)) {
+                    stack.push((next, dir));
+                }
+                continue;
+            }
+
+            let mut out = [false; 4];
+            if beam::is_filter(tile) {
+                out[dir] = true;
+            } else if 0 != content[idx] {
+                let (accept, emit) = reflector_arms(content[idx]);
+                // the beam comes from the side opposite to its travel direction
+                if accept[opposite(dir)] { out = emit; }
+            } else if self.decided[idx] {
+                out[dir] = true;
+            } else {
+                out = [true; 4];
+            }
+
+            for (d, &arm) in out.iter().enumerate() {
+                if !arm { continue; }
+                if let Some(next) = step(idx, d, sz) {
+                    stack.push((next, d));
+                }
+            }
+        }
+
+        false
+    }
+}
+
+
+// solve every map in levels.dat and print a report. the designed path search
+// settles whether a map is solvable and finds a first solution, then the full
+// search counts solutions within max_nodes
+pub fn report(filename: &str, only: Option<usize>, max_nodes: usize) {
+
+    let blocks = match levels::load_blocks(filename) {
+        Ok(blocks) => blocks,
+        Err(err) => {
+            println!("{filename}: {err}");
+            return;
+        }
+    };
+
+    let config = SolverConfig {
+        max_nodes,
+        ..SolverConfig::new()
+    };
+
+    let mut nsolved = 0;
+    let mut nunique = 0;
+    let mut nunsolved = 0;
+    let mut nincomplete = 0;
+
+    for (i, block) in blocks.iter().enumerate() {
+        if let Some(idx) = only {
+            if idx != i { continue; }
+        }
+
+        let path = path_solve(block, HINT_MAX_NODES, None);
+        let res = solve(block, &config);
+
+        let count = match res.stats.complete {
+            true => format!("{}", res.count),
+            false => format!(">={}", res.count.max(path.first.is_some() as usize)),
+        };
+
+        let mut first = String::from("-");
+        if let Some(sol) = path.first.as_ref().or(res.first.as_ref()) {
+            first = format!("source {}:", sol.source);
+            for p in &sol.placements {
+                first = format!("{first} {}/{}", p.0, p.1);
+            }
+        }
+
+        println!("map {i}: npcs {}, sz {}, norbs {}, path {} nodes {} ms, solutions {count}, nodes {}, depth {}, {} ms, {first}",
+            block.npcs, block.sz, block.norbs, path.stats.nodes, path.stats.micros / 1000,
+            res.stats.nodes, res.stats.max_depth, res.stats.micros / 1000);
+
+        let solved = path.first.is_some() || 0 < res.count;
+        if solved { nsolved += 1; }
+        if 1 == res.count && res.stats.complete { nunique += 1; }
+        if !solved && res.stats.complete { nunsolved += 1; }
+        if !res.stats.complete { nincomplete += 1; }
+    }
+
+    println!("solved: {nsolved}, unique: {nunique}, unsolvable: {nunsolved}, count incomplete: {nincomplete}");
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // two orbs on a row with a cell between them, an orb stops the beam so
+    // only a splitter in that cell lights both
+    fn split_block(pieces: Vec<u16>) -> Block {
+        let mut map = vec![CELL_FLOOR; 5 * 5];
+        map[6] = CELL_ORB;
+        map[8] = CELL_ORB;
+        Block { sz: 5, norbs: 2, npcs: pieces.len(), pieces, listed: true, par: 0, map }
+    }
+
+    #[test]
+    fn solves_a_split() {
+        let block = split_block(vec![REFLECTOR_U]);
+        let res = solve(&block, &SolverConfig::new());
+        assert!(res.stats.complete);
+        assert_eq!(1, res.count);
+
+        // the first solution lights every orb when played back
+        let first = res.first.unwrap();
+        let pieces: Vec<PieceData> = first.placements.iter()
+            .map(|(idx, tile)| PieceData { tile: *tile, board_idx: *idx as u16, ..Default::default() })
+            .collect();
+        let scene = scene::build_scene_empty(&block);
+        assert_eq!(vec![6, 8], beam::trace(&scene, &pieces, first.source).lit_orbs);
+    }
+
+    #[test]
+    fn no_pieces_no_solution() {
+        let res = solve(&split_block(Vec::new()), &SolverConfig::new());
+        assert!(res.stats.complete);
+        assert_eq!(0, res.count);
+        assert!(res.first.is_none());
+    }
+
+    #[test]
+    fn path_follows_the_orientation() {
+        // the path turns at the top right, a diagonal mirror of the board keeps
+        // the orbs but moves the turn to the bottom left
+        let mut map = vec![CELL_FLOOR; 5 * 5];
+        map[6] = CELL_ORB;
+        map[8] = CELL_PATH;
+        map[18] = CELL_ORB;
+        let block = Block { sz: 5, norbs: 2, npcs: 1, pieces: vec![REFLECTOR_TR], listed: true, par: 0, map };
+
+        for orient in 0..8 {
+            let layout = Block { map: scene::oriented_map(&block, orient), ..block.clone() };
+            let scene = scene::build_scene_empty(&layout);
+            let path = path_scene(&block, &scene, orient, &[]);
+            for yy in 1..4 {
+                for xx in 1..4 {
+                    let idx = yy * 5 + xx;
+                    assert_eq!(CELL_FLOOR == layout.map[idx], BLOCK == path.tilemap[idx], "orient {orient} cell {idx}");
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,179 @@
+use std::fs::File;
+use std::io::{self, BufRead, Write};
+
+// game::update runs at a fixed 1200 hz, one tick per update
+pub const TICKS_PER_SECOND: u64 = 1200;
+
+pub const PB_FILE: &str = "speedrun.dat";
+
+// personal bests, levels are indexed by displayed level - 1
+#[derive(Default, Clone)]
+pub struct PersonalBests {
+    pub run: u64, // full run, 0 until one is finished
+    pub segments: Vec<u64>, // best time spent on each level
+    pub run_splits: Vec<u64>, // splits of the best full run, 0 for levels it didn't play
+}
+
+// one split, taken when a level is won
+#[derive(Clone, Copy)]
+pub struct Split {
+    pub level: usize,
+    pub ticks: u64, // since the run started
+    pub best: bool, // new best time spent on this level
+}
+
+#[derive(Default)]
+pub struct SpeedrunData {
+    pub enabled: bool, // picked on the main menu
+    pub keep_pbs: bool, // bests come from and go to PB_FILE
+    pub running: bool,
+    pub ticks: u64,
+    pub splits: Vec<Split>,
+    pub pb: PersonalBests,
+    pub compare: Vec<u64>, // best run splits as they were when this run started, by level like PersonalBests
+    pub run_pb: bool, // the finished run beat the full run best
+}
+
+
+// start timing from the start button, bests are read fresh every run
+pub fn start(data: &mut SpeedrunData) {
+    data.running = true;
+    data.ticks = 0;
+    data.splits.clear();
+    data.run_pb = false;
+    if data.keep_pbs {
+        data.pb = load_pbs(PB_FILE);
+    }
+    data.compare = data.pb.run_splits.clone();
+}
+
+
+pub fn tick(data: &mut SpeedrunData) {
+    if data.running {
+        data.ticks += 1;
+    }
+}
+
+
+// record a split for the level just won, the final level ends the run
+pub fn split(data: &mut SpeedrunData, level: usize, last: bool) {
+
+    if !data.running || 0 == level { return; }
+
+    let prev = match data.splits.last() {
+        Some(s) => s.ticks,
+        None => 0,
+    };
+    let segment = data.ticks - prev;
+
+    let k = level - 1;
+    if data.pb.segments.len() <= k {
+        data.pb.segments.resize(level, 0);
+    }
+    let best = 0 == data.pb.segments[k] || segment < data.pb.segments[k];
+    if best {
+        data.pb.segments[k] = segment;
+    }
+
+    data.splits.push(Split { level, ticks: data.ticks, best });
+
+    if last {
+        data.running = false;
+        if 0 == data.pb.run || data.ticks < data.pb.run {
+            data.pb.run = data.ticks;
+            data.pb.run_splits = vec![0; data.pb.segments.len()];
+            for s in &data.splits {
+                data.pb.run_splits[s.level - 1] = s.ticks;
+            }
+            data.run_pb = true;
+        }
+    }
+
+    if !data.keep_pbs { return; }
+
+    if let Err(err) = save_pbs(PB_FILE, &data.pb) {
+        println!("{PB_FILE}: {err}, personal bests not saved");
+    }
+}
+
+
+// split time against the best run's split for the same level, if there is one
+pub fn delta(data: &SpeedrunData, split: &Split) -> Option<i64> {
+    let pb = *data.compare.get(split.level - 1).filter(|t| 0 != **t)?;
+    Some(split.ticks as i64 - pb as i64)
+}
+
+
+// m:ss.cc
+pub fn format_time(ticks: u64) -> String {
+    let cs = ticks * 100 / TICKS_PER_SECOND;
+    format!("{}:{:02}.{:02}", cs / 6000, (cs / 100) % 60, cs % 100)
+}
+
+// +s.cc or -s.cc
+pub fn format_delta(ticks: i64) -> String {
+    let sign = if 0 > ticks { '-' } else { '+' };
+    let cs = ticks.unsigned_abs() * 100 / TICKS_PER_SECOND;
+    format!("{sign}{}.{:02}", cs / 100, cs % 100)
+}
+
+
+// run,<ticks>, then level,<level>,<best segment>,<best run split> per level.
+// a missing file just means no bests yet
+pub fn load_pbs(filename: &str) -> PersonalBests {
+
+    let mut pb = PersonalBests::default();
+
+    let file = match File::open(filename) {
+        Ok(file) => file,
+        Err(_) => return pb,
+    };
+
+    let reader = io::BufReader::new(file);
+    for line in reader.lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(_) => break,
+        };
+        let split: Vec<&str> = line.trim().split(',').collect();
+        let field = |i: usize| split.get(i).and_then(|v| v.parse::<u64>().ok());
+
+        match split[0] {
+            "run" => pb.run = field(1).unwrap_or(0),
+            "level" => {
+                if let (Some(level), Some(segment), Some(run_split)) = (field(1), field(2), field(3)) {
+                    if 0 == level { continue; }
+                    let k = level as usize - 1;
+                    if pb.segments.len() <= k {
+                        pb.segments.resize(k + 1, 0);
+                        pb.run_splits.resize(k + 1, 0);
+                    }
+                    pb.segments[k] = segment;
+                    pb.run_splits[k] = run_split;
+                }
+            },
+            _ => (),
+        }
+    }
+
+    // splits only count as a whole run
+    if 0 == pb.run {
+        pb.run_splits.clear();
+    }
+
+    pb
+}
+
+
+pub fn save_pbs(filename: &str, pb: &PersonalBests) -> io::Result<()> {
+
+    let mut file = File::create(filename)?;
+
+    writeln!(file, "run,{}", pb.run)?;
+    for k in 0..pb.segments.len() {
+        let run_split = pb.run_splits.get(k).copied().unwrap_or(0);
+        writeln!(file, "level,{},{},{}", k + 1, pb.segments[k], run_split)?;
+    }
+
+    Ok(())
+}
@@ -1,15 +1,20 @@
 use crate::mgfw;
 use crate::mgfw::ecs::mgui;
 
+use super::beam;
 use super::game;
 use super::scene;
 use super::game_state;
+use super::progress;
+use super::share;
+use super::speedrun;
 use super::enums::*;
 
 const MENU_INVALID: u8 = 0;
 //
 const MENU_MAIN: u8 = 1;
 const MENU_GAME: u8 = 2;
+const MENU_SELECT: u8 = 3;
 const MENU_NOTIFICATION_POPUP: u8 = 19;
 
 const TRANSITION_MODE_INVALID: u8 = 0;
@@ -18,13 +23,21 @@ const TRANSITION_MODE_FLASH: u8 = 1;
 pub struct UIData {
     click_delay: u8,
     holding_idx: u8,
+    holding_from: u16, // board idx the piece in hand was picked up from, BOARD_IDX_INVALID from the inventory
+    dragging: bool, // the piece in hand was picked up by a press that is still held
+    drag_x: i32, // where the drag started
+    drag_y: i32,
     inventory_idx: u8,
-    board_idx: u8,
+    board_idx: u16,
     menu_hover_idx: u8,
-    source_hover: u8,
-    beam_origin: u8,
+    source_hover: u16,
+    beam_origin: u16,
+    beam_step: u16,
     beam_alpha: f32,
     beam_hold: bool,
+    hint_piece: u8,
+    hint_idx: u16,
+    hint_timer: u8,
 
     menu: u8,
     win_timer: u8,
@@ -32,6 +45,12 @@ pub struct UIData {
     transition_timer: u16,
     transition_offsets: [u8; 8 * 11],
 
+    select_page: usize,
+
+    pointer_keys: bool, // the pointer was last moved by keys or a gamepad
+    pointer_x: i32,
+    pointer_y: i32,
+
     board_left: i32,
     board_top: i32,
     menu_left: i32,
@@ -39,28 +58,95 @@ pub struct UIData {
 }
 
 
+// board cell size in pixels, halved until a big board fits in the view
+fn board_cell(sz: usize) -> usize {
+    let mut cell = TILE_SZ;
+    while TILE_SZ_QTR < cell && MAP_VIEW_WIDTH * TILE_SZ < sz * cell {
+        cell /= 2;
+    }
+    cell
+}
+
+
 pub fn initialize(cache: &mut game::GameData, world: &mut mgfw::ecs::World) {
     
     let data = &mut cache.ui_data;
 
     data.click_delay = 0;
     data.holding_idx = HOLDING_INVALID;
+    data.holding_from = BOARD_IDX_INVALID;
+    data.dragging = false;
+    data.drag_x = -1;
+    data.drag_y = -1;
     data.inventory_idx = INVENTORY_IDX_INVALID;
     data.board_idx = BOARD_IDX_INVALID;
     data.menu_hover_idx = MENU_HOVER_INVALID;
     data.source_hover = BOARD_IDX_INVALID;
     data.beam_origin = BOARD_IDX_INVALID;
+    data.beam_step = 0;
     data.beam_alpha = 0.0;
     data.beam_hold = false;
+    data.hint_piece = HOLDING_INVALID;
+    data.hint_idx = BOARD_IDX_INVALID;
+    data.hint_timer = 0;
 
     data.menu = MENU_MAIN;
     data.transition_mode = TRANSITION_MODE_INVALID;
     data.transition_timer = 0;
     data.win_timer = 0;
+    data.select_page = 0;
+
+    data.pointer_keys = false;
+    data.pointer_x = -1;
+    data.pointer_y = -1;
 
     data.board_left = 48;
     data.board_top = 48;
 
+    bind_actions(world);
+}
+
+
+pub const BINDINGS_FILE: &str = "bindings.dat";
+
+// default inputs for every action, the bindings file moves them. a missing
+// file is written out with the defaults so there is one to edit
+#[rustfmt::skip]
+fn bind_actions(world: &mut mgfw::ecs::World) {
+
+    let actions: [(u8, &str, &[&str]); 22] = [
+        (ACTION_QUIT, "quit", &["escape"]),
+        (ACTION_DEBUG, "debug", &["f3"]),
+        (ACTION_EXPORT, "export", &["f5"]),
+        (ACTION_EXPORT_SOLUTION, "export_solution", &["f6"]),
+        (ACTION_IMPORT, "import", &["f7"]),
+        (ACTION_CLICK, "click", &["mouse_left", "space", "pad_a"]),
+        (ACTION_FIRE, "fire", &["return", "pad_x"]),
+        (ACTION_CURSOR_UP, "cursor_up", &["up", "pad_up"]),
+        (ACTION_CURSOR_DOWN, "cursor_down", &["down", "pad_down"]),
+        (ACTION_CURSOR_LEFT, "cursor_left", &["left", "pad_left"]),
+        (ACTION_CURSOR_RIGHT, "cursor_right", &["right", "pad_right"]),
+        (ACTION_UNDO, "undo", &["z", "pad_b"]),
+        (ACTION_REDO, "redo", &["y", "pad_y"]),
+        (ACTION_ROTATE, "rotate", &["r"]),
+        (ACTION_FLIP_H, "flip_h", &["f"]),
+        (ACTION_FLIP_V, "flip_v", &["v"]),
+        (ACTION_RESET, "reset", &["c"]),
+        (ACTION_NEW_BOARD, "new_board", &["n"]),
+        (ACTION_HINT, "hint", &["h"]),
+        (ACTION_BRANCH_PREV, "branch_prev", &["pgup"]),
+        (ACTION_BRANCH_NEXT, "branch_next", &["pgdn"]),
+        (ACTION_RETURN_PIECE, "return_piece", &["mouse_right"]),
+    ];
+    for (action, name, inputs) in actions {
+        world.input.define(action, name, inputs);
+    }
+
+    if world.input.load(BINDINGS_FILE).is_err() {
+        if let Err(err) = world.input.save(BINDINGS_FILE) {
+            println!("{BINDINGS_FILE}: {err}");
+        }
+    }
 }
 
 
@@ -75,7 +161,9 @@ pub fn update(cache: &mut game::GameData, heap: &mut game::GameDataHeap, world:
     let my = world.mouse_y;
 
     if MENU_MAIN == cache.ui_data.menu {
-        update_main(cache, heap, world);
+        update_main(cache, world);
+    } else if MENU_SELECT == cache.ui_data.menu {
+        update_select(cache, heap, world);
     }
 
     if TRANSITION_MODE_INVALID != cache.ui_data.transition_mode {
@@ -89,17 +177,22 @@ pub fn update(cache: &mut game::GameData, heap: &mut game::GameDataHeap, world:
     if MENU_GAME != cache.ui_data.menu { return false; }
 
     // update ui positioning
-    let uiwidth = (4 + 1 + heap.scene_data.sz) * 16;
-    let uiheight = 4 * heap.scene_data.num_pcs as usize + 4 * 18 + 2;
+    let cell = board_cell(heap.scene_data.sz);
+    let uiwidth = (4 + 1) * 16 + heap.scene_data.sz * cell;
+    let uiheight = 16 * (heap.scene_data.num_pcs as usize).div_ceil(4) + 4 * 18 + 2;
     cache.ui_data.board_left = (SCREEN_XRES_HALF - uiwidth / 2) as i32;
-    cache.ui_data.board_top = (SCREEN_YRES_HALF - (heap.scene_data.sz * 8 - 8)) as i32;
+    cache.ui_data.board_top = (SCREEN_YRES_HALF - (heap.scene_data.sz * cell / 2 - 8)) as i32;
 
-    cache.ui_data.menu_left = cache.ui_data.board_left + (heap.scene_data.sz as i32 + 1) * 16;
+    cache.ui_data.menu_left = cache.ui_data.board_left + (heap.scene_data.sz * cell) as i32 + 16;
     cache.ui_data.menu_top = (SCREEN_YRES_HALF - uiheight / 2) as i32 + 8;
 
     world.entity_set_position_xy(cache.tilemap_ent, cache.ui_data.board_left as f32, cache.ui_data.board_top as f32);
     world.entity_set_position_xy(cache.reflector_ent, cache.ui_data.board_left as f32, cache.ui_data.board_top as f32);
     world.entity_set_position_xy(cache.beam_ent, cache.ui_data.board_left as f32, cache.ui_data.board_top as f32);
+    world.entity_set_scale_xy(cache.tilemap_ent, cell as f32, cell as f32);
+    world.entity_set_scale_xy(cache.reflector_ent, cell as f32, cell as f32);
+    world.entity_set_scale_xy(cache.beam_ent, cell as f32, cell as f32);
+    world.entity_set_scale_xy(cache.source_ent, cell as f32, cell as f32);
     
     world.entity_set_position_xy(cache.game_menu_ent, (cache.ui_data.menu_left + 5) as f32, (cache.ui_data.menu_top + 0 * 16) as f32);
     world.entity_set_position_xy(cache.game_menu2_ent, (cache.ui_data.menu_left + 5) as f32, cache.ui_data.menu_top as f32 + 2.5 * 18.0);
@@ -108,16 +201,33 @@ pub fn update(cache: &mut game::GameData, heap: &mut game::GameDataHeap, world:
 
     world.entity_set_position_xy(cache.logo_mini_ent, cache.ui_data.menu_left as f32 + 32.0, cache.ui_data.menu_top as f32 - 18.0);
 
-    if !cache.final_level {
+    if 0 != cache.player_data.daily {
+        let date = cache.player_data.daily;
+        world.entity_set_text(cache.level_ent, format!("Daily: {}-{:02}-{:02}", date / 10000, date / 100 % 100, date % 100));
+        if 0 < cache.player_data.hints {
+            world.entity_set_text(cache.level_ent, format!("Daily: {}-{:02}-{:02} (hints: {})", date / 10000, date / 100 % 100, date % 100, cache.player_data.hints));
+        }
+    } else if heap.shared.is_some() {
+        world.entity_set_text(cache.level_ent, String::from("Shared board"));
+        if 0 < cache.player_data.hints {
+            world.entity_set_text(cache.level_ent, format!("Shared board (hints: {})", cache.player_data.hints));
+        }
+    } else if !cache.final_level {
         world.entity_set_text(cache.level_ent, format!("Level: {}", cache.player_data.level_displayed));
+        if 0 < cache.player_data.hints {
+            world.entity_set_text(cache.level_ent, format!("Level: {} (hints: {})", cache.player_data.level_displayed, cache.player_data.hints));
+        }
     } else {
         world.entity_set_text(cache.level_ent, format!("FINAL LEVEL!"));
     }
     let textwidth = world.text_get_width(cache.level_ent);
-    world.entity_set_position_xy(cache.level_ent, (cache.ui_data.board_left as usize + 8 * heap.scene_data.sz - textwidth / 2) as f32, cache.ui_data.board_top as f32 - 16.0);
+    world.entity_set_position_xy(cache.level_ent, (cache.ui_data.board_left as usize + cell * heap.scene_data.sz / 2 - textwidth / 2) as f32, cache.ui_data.board_top as f32 - 16.0);
 
     if 0 != cache.frame % 8 { return false; } // 150 hz
 
+    update_speedrun(cache, heap, world);
+    update_debug(cache, world);
+
     if cache.ui_data.click_delay > 0 {
         cache.ui_data.click_delay -= 1;
     }
@@ -158,9 +268,24 @@ pub fn update(cache: &mut game::GameData, heap: &mut game::GameDataHeap, world:
 
     world.entity_set_tilemap(cache.game_menu_ent, cache.ui_tiles_ent, 3, &menu_data);
 
+    ///////////////////////////////////////////////////////////////////////////
+    // hint and par search
+    game_state::poll_par(cache, heap);
+    if let Some(hint) = game_state::poll_hint(cache, heap) {
+        cache.ui_data.hint_piece = hint.piece;
+        cache.ui_data.hint_idx = hint.board_idx;
+        cache.ui_data.hint_timer = 64;
+    }
+    if cache.ui_data.hint_timer > 0 {
+        cache.ui_data.hint_timer -= 1;
+    } else {
+        cache.ui_data.hint_timer = 64;
+    }
+
     ///////////////////////////////////////////////////////////////////////////
     // game menu 2
-    let mut menu_data = vec![4, 0, 8];
+    let mut menu_data = vec![4, 17, 8];
+    if heap.hint_job.is_some() && 31 < cache.ui_data.hint_timer { menu_data[1] = 18; }
     
     if HOLDING_INVALID == cache.ui_data.holding_idx {
         let menu_left = cache.ui_data.menu_left + 5;
@@ -174,7 +299,11 @@ pub fn update(cache: &mut game::GameData, heap: &mut game::GameDataHeap, world:
                 menu_data[0] += 8;
                 cache.ui_data.menu_hover_idx = MENU_HOVER_RESET;
             
-            } else if 2 == xx {
+            } else if 1 == xx {
+                menu_data[1] = 18;
+                cache.ui_data.menu_hover_idx = MENU_HOVER_HINT;
+
+            } else if 2 == xx && 0 == cache.player_data.daily && heap.shared.is_none() {
                 menu_data[2] += 8;
                 cache.ui_data.menu_hover_idx = MENU_HOVER_TRASH;
             }
@@ -183,6 +312,9 @@ pub fn update(cache: &mut game::GameData, heap: &mut game::GameDataHeap, world:
     
     world.entity_set_tilemap(cache.game_menu2_ent, cache.ui_tiles_ent, 3, &menu_data);
 
+    update_branch(cache, heap, world);
+    update_pointer(cache, heap, world);
+
     if TRANSITION_MODE_INVALID == cache.ui_data.transition_mode && 0 < cache.blackout_alpha {
         cache.blackout_alpha -= 1;
         world.entity_set_alpha(cache.blackout_ent, cache.blackout_alpha as f32 / 30.0);
@@ -195,23 +327,23 @@ pub fn update(cache: &mut game::GameData, heap: &mut game::GameDataHeap, world:
     
     let sz = heap.scene_data.sz;
     let sz2 = heap.scene_data.sz2;
-    let sz16 = sz as i32 * 16;
+    let cell = board_cell(sz) as i32;
 
     let board_left = cache.ui_data.board_left;
     let board_top = cache.ui_data.board_top;
-    let board_right = board_left + sz16;
-    let board_bottom = board_top + sz16;
+    let board_right = board_left + sz as i32 * cell;
+    let board_bottom = board_top + sz as i32 * cell;
     
     ///////////////////////////////////////////////////////////////////////////
     // hovering location
     cache.ui_data.board_idx = BOARD_IDX_INVALID;
 
-    if mx > board_left + 16 && mx < board_right - 16 && my > board_top + 16 && my < board_bottom - 16 {
-        let xx = ((mx - board_left - 16) as f32 / 16.0).floor() as usize;
-        let yy = ((my - board_top - 16) as f32 / 16.0).floor() as usize;
+    if mx > board_left + cell && mx < board_right - cell && my > board_top + cell && my < board_bottom - cell {
+        let xx = ((mx - board_left - cell) as f32 / cell as f32).floor() as usize;
+        let yy = ((my - board_top - cell) as f32 / cell as f32).floor() as usize;
         let idx = (1 + yy) * sz + xx + 1;
-        if heap.scene_data.tilemap[idx] == FLOOR || heap.scene_data.tilemap[idx] == FLOOR+1 {
-            cache.ui_data.board_idx = idx as u8;
+        if scene::is_floor(heap.scene_data.tilemap[idx]) {
+            cache.ui_data.board_idx = idx as u16;
         }
     }
 
@@ -225,17 +357,19 @@ pub fn update(cache: &mut game::GameData, heap: &mut game::GameDataHeap, world:
         let wx = mx - 8;
         let wy = my - 8;
         world.entity_set_position_xy(cache.holding_ent, wx as f32, wy as f32);
+        world.entity_set_scale_xy(cache.holding_ent, 16.0, 16.0);
 
         // snap
         if BOARD_IDX_INVALID != cache.ui_data.board_idx {
             let idx = cache.ui_data.board_idx;
-            let xx = idx % sz as u8;
-            let yy = (idx - xx) / sz as u8;
+            let xx = idx % sz as u16;
+            let yy = (idx - xx) / sz as u16;
 
-            let xx = xx as f32 * 16.0 + board_left as f32;
-            let yy = yy as f32 * 16.0 + board_top as f32;
+            let xx = (xx as i32 * cell + board_left) as f32;
+            let yy = (yy as i32 * cell + board_top) as f32;
 
             world.entity_set_position_xy(cache.holding_ent, xx, yy);
+            world.entity_set_scale_xy(cache.holding_ent, cell as f32, cell as f32);
         }
         
     } else {
@@ -249,13 +383,13 @@ pub fn update(cache: &mut game::GameData, heap: &mut game::GameDataHeap, world:
     let inv_left = cache.ui_data.menu_left;
     let inv_right = inv_left + 4 * 16;
     let inv_top = cache.ui_data.menu_top + 4 * 18 + 2;
-    let inv_bottom = inv_top + 4 * heap.scene_data.num_pcs as i32;
+    let inv_bottom = inv_top + 16 * (heap.scene_data.num_pcs as usize).div_ceil(4) as i32;
 
     if mx > inv_left && mx < inv_right && my > inv_top && my < inv_bottom {
         let xx = ((mx - inv_left) as f32 / 16.0).floor() as u8;
         let yy = ((my - inv_top) as f32 / 16.0).floor() as u8;
         let idx = yy * 4 + xx;
-        if BOARD_IDX_INVALID == cache.pieces[idx as usize].board_idx {
+        if (idx as usize) < heap.scene_data.num_pcs as usize && BOARD_IDX_INVALID == cache.pieces[idx as usize].board_idx {
             cache.ui_data.inventory_idx = idx;
         }
     }
@@ -263,15 +397,11 @@ pub fn update(cache: &mut game::GameData, heap: &mut game::GameDataHeap, world:
     
     ///////////////////////////////////////////////////////////////////////////
     // hovering over cell
-    let mut pcs = match heap.scene_data.num_pcs {
-        8 => vec![17, 18, 23, 22, 19, 20, 24, 21],
-        16 => vec![17, 18, 23, 22, 19, 20, 24, 21, 17, 18, 23, 22, 19, 20, 24, 21],
-        24 => vec![17, 18, 23, 22, 19, 20, 24, 21, 17, 18, 23, 22, 19, 20, 24, 21, 17, 18, 23, 22, 19, 20, 24, 21],
-        _ => vec![],
-    };
+    let npcs = heap.scene_data.num_pcs as usize;
+    let mut pcs = scene::inventory_tiles(&cache.pieces[0..npcs]);
     
     // erase pieces from inventory if they are on the game board
-    for i in 0..pcs.len() {
+    for i in 0..npcs {
         if BOARD_IDX_INVALID != cache.pieces[i].board_idx || i == cache.ui_data.holding_idx as usize {
             pcs[i] = 0;
         }
@@ -280,16 +410,55 @@ pub fn update(cache: &mut game::GameData, heap: &mut game::GameDataHeap, world:
         let idx = cache.ui_data.inventory_idx as usize;
         if 0 != pcs[idx] { pcs[idx] += 8; }
     }
-    world.entity_set_tilemap(cache.pieces_ent, cache.tileset_ent, 4, &pcs);
 
     // update board pieces
     heap.scene_data.reflectors = vec![0; sz2];
-    for i in 0..pcs.len() {
+    for i in 0..npcs {
         if BOARD_IDX_INVALID != cache.pieces[i].board_idx {
             let idx = cache.pieces[i].board_idx as usize;
-            heap.scene_data.reflectors[idx] = cache.pieces[i].tile;
+            let mut tile = cache.pieces[i].tile;
+            if cache.pieces[i].locked { tile = REFLECTOR_LOCKED + (tile - REFLECTOR_TL); }
+            heap.scene_data.reflectors[idx] = tile;
         }
     }
+
+    ///////////////////////////////////////////////////////////////////////////
+    // blink the hinted piece and its destination
+    world.entity_set_visibility(cache.hint_ent, false);
+    let hinting = HOLDING_INVALID != cache.ui_data.hint_piece || BOARD_IDX_INVALID != cache.ui_data.hint_idx;
+    if hinting && 31 < cache.ui_data.hint_timer {
+        let piece = cache.ui_data.hint_piece as usize;
+        let dst = cache.ui_data.hint_idx as usize;
+        let mut tile = 0;
+
+        if HOLDING_INVALID as usize == piece {
+            // everything is in place, point at the source
+            tile = heap.scene_data.tilemap[dst] + 4;
+
+        } else if piece != cache.ui_data.holding_idx as usize {
+            tile = cache.pieces[piece].tile + 8;
+            let src = cache.pieces[piece].board_idx as usize;
+            if BOARD_IDX_INVALID as usize == src {
+                pcs[piece] = tile;
+            } else {
+                heap.scene_data.reflectors[src] = tile;
+            }
+        }
+
+        if 0 != tile {
+            // back to its inventory slot or onto the board
+            let (xx, yy, scale) = if BOARD_IDX_INVALID as usize == dst {
+                (cache.ui_data.menu_left + (piece % 4) as i32 * 16, inv_top + (piece / 4) as i32 * 16, 16)
+            } else {
+                (board_left + (dst % sz) as i32 * cell, board_top + (dst / sz) as i32 * cell, cell)
+            };
+            world.entity_set_position_xy(cache.hint_ent, xx as f32, yy as f32);
+            world.entity_set_scale_xy(cache.hint_ent, scale as f32, scale as f32);
+            world.entity_set_tilemap(cache.hint_ent, cache.tileset_ent, 1, &vec![tile]);
+            world.entity_set_visibility(cache.hint_ent, true);
+        }
+    }
+    world.entity_set_tilemap(cache.pieces_ent, cache.tileset_ent, 4, &pcs);
     world.entity_set_tilemap(cache.reflector_ent, cache.tileset_ent, sz, &heap.scene_data.reflectors);
 
     // update beam source
@@ -297,14 +466,14 @@ pub fn update(cache: &mut game::GameData, heap: &mut game::GameDataHeap, world:
     world.entity_set_visibility(cache.source_ent, false);
     if BOARD_IDX_INVALID == cache.ui_data.beam_origin && HOLDING_INVALID == cache.ui_data.holding_idx &&
         mx > board_left && mx < board_right && my > board_top && my < board_bottom {
-        let xx = ((mx - board_left) as f32 / 16.0).floor() as usize;
-        let yy = ((my - board_top) as f32 / 16.0).floor() as usize;
+        let xx = ((mx - board_left) as f32 / cell as f32).floor() as usize;
+        let yy = ((my - board_top) as f32 / cell as f32).floor() as usize;
         let idx = yy * sz + xx;
         let tile = heap.scene_data.tilemap[idx];
-        if SOURCE_U == tile || SOURCE_D == tile || SOURCE_R == tile || SOURCE_L == tile {
-            cache.ui_data.source_hover = idx as u8;
-            let xx = board_left as usize + xx * 16;
-            let yy = board_top as usize + yy * 16;
+        if beam::is_source(tile) {
+            cache.ui_data.source_hover = idx as u16;
+            let xx = board_left + xx as i32 * cell;
+            let yy = board_top + yy as i32 * cell;
             world.entity_set_position_xy(cache.source_ent, xx as f32, yy as f32);
             world.entity_set_tilemap(cache.source_ent, cache.tileset_ent, 1, &vec![tile + 4]);
         }
@@ -321,265 +490,19 @@ pub fn update(cache: &mut game::GameData, heap: &mut game::GameDataHeap, world:
         // starting location
         let xx = orig % sz;
         let yy = (orig - xx) / sz;
-        let xx = board_left as f32 + xx as f32 * 16.0;
-        let yy = board_top as f32 + yy as f32 * 16.0;
+        let xx = (board_left + xx as i32 * cell) as f32;
+        let yy = (board_top + yy as i32 * cell) as f32;
         let tile = heap.scene_data.tilemap[orig];
         world.entity_set_position_xy(cache.source_ent, xx, yy);
         world.entity_set_tilemap(cache.source_ent, cache.tileset_ent, 1, &vec![tile + 4]);
         
-        heap.scene_data.beams[orig] = BEAM_STOP_U + (tile - SOURCE_U);
-
-        //loop {
-            let mut keep_going = false;
-'outer:
-            for y in 1..sz-1 {
-                for x in 1..sz-1 {
-                    let idx = y * sz + x;
-                    
-                    let mut tile = 255;
-                    let up = idx - sz;
-                    let down = idx + sz;
-                    let left = idx - 1;
-                    let right = idx + 1;
-
-                    let beam_up = heap.scene_data.beams[up];
-                    let beam_down = heap.scene_data.beams[down];
-                    let beam_left = heap.scene_data.beams[left];
-                    let beam_right = heap.scene_data.beams[right];
-
-                    let clipped = heap.scene_data.clipping[idx] as u16;
-                    
-                    if 0 != heap.scene_data.beams[idx] {
-
-                        // only allow crossover
-                        if BEAM_H == heap.scene_data.beams[idx] {
-                            // anything above or below going vertical?
-                            if BEAM_V == beam_up || BEAM_V == beam_down ||
-                               BEAM_SPLIT_TL == beam_up || BEAM_SPLIT_TR == beam_up ||
-                               BEAM_SPLIT_BL == beam_down || BEAM_SPLIT_BR == beam_down ||
-                               BEAM_SPLIT_L == beam_up || BEAM_SPLIT_R == beam_up ||
-                               BEAM_SPLIT_L == beam_down || BEAM_SPLIT_R == beam_down ||
-                               BEAM_SPLIT_X == beam_up || BEAM_SPLIT_X == beam_down ||
-                               orig == up || orig == down
-                            {
-                                tile = BEAM_SPLIT_X;
-                            }
-                        
-                        } else if BEAM_V == heap.scene_data.beams[idx] {
-                            if BEAM_H == beam_left || BEAM_H == beam_right ||
-                            BEAM_SPLIT_TL == beam_left || BEAM_SPLIT_BL == beam_left ||
-                            BEAM_SPLIT_TR == beam_right || BEAM_SPLIT_BR == beam_right ||
-                            BEAM_SPLIT_U == beam_left || BEAM_SPLIT_U == beam_right ||
-                            BEAM_SPLIT_D == beam_left || BEAM_SPLIT_D == beam_right ||
-                            BEAM_SPLIT_X == beam_left || BEAM_SPLIT_X == beam_right ||
-                            orig == left || orig == right
-                            {
-                                tile = BEAM_SPLIT_X;
-                            }
-                        }
-
-                        if 255 != tile {
-                            heap.scene_data.beams[idx] = tile;
-                            keep_going = true;
-                        }
-                        continue;
-                    }
-
-                    if CLIPPING_NONE == clipped as u8 {                    
-                        // check for next to source
-                        if up == orig || down == orig { tile = BEAM_V; }
-                        if left == orig || right == orig { tile = BEAM_H; }
-
-                        if BEAM_V == beam_up || BEAM_V == beam_down || BEAM_SPLIT_X == beam_up || BEAM_SPLIT_X == beam_down {
-                            tile = BEAM_V;
-                        }
-
-                        if BEAM_H == beam_left || BEAM_H == beam_right || BEAM_SPLIT_X == beam_left || BEAM_SPLIT_X == beam_right {
-                            tile = BEAM_H;
-                        }
-
-                        if BEAM_SPLIT_BL == beam_left || BEAM_SPLIT_TL == beam_left || BEAM_SPLIT_BR == beam_right || BEAM_SPLIT_TR == beam_right ||
-                           BEAM_SPLIT_U == beam_left || BEAM_SPLIT_D == beam_left || BEAM_SPLIT_U == beam_right || BEAM_SPLIT_D == beam_right {
-                            tile = BEAM_H;
-                        }
-
-                        if BEAM_SPLIT_BL == beam_down || BEAM_SPLIT_TL == beam_up || BEAM_SPLIT_BR == beam_down || BEAM_SPLIT_TR == beam_up ||
-                           BEAM_SPLIT_L == beam_up || BEAM_SPLIT_R == beam_up || BEAM_SPLIT_L == beam_down || BEAM_SPLIT_R == beam_down {
-                            tile = BEAM_V;
-                        }
-                    
-                    // blocks
-                    } else if BLOCK == clipped {
-                        if BEAM_V == beam_up ||
-                           BEAM_SPLIT_X == beam_up ||
-                           BEAM_SPLIT_TL == beam_up ||
-                           BEAM_SPLIT_TR == beam_up ||
-                           BEAM_SPLIT_L == beam_up ||
-                           BEAM_SPLIT_R == beam_up ||
-                           orig == up {
-                            tile = BEAM_STOP_U;
-                        }
-                        if BEAM_V == beam_down ||
-                           BEAM_SPLIT_X == beam_down ||
-                           BEAM_SPLIT_BL == beam_down ||
-                           BEAM_SPLIT_BR == beam_down ||
-                           BEAM_SPLIT_L == beam_down ||
-                           BEAM_SPLIT_R == beam_down ||
-                           orig == down {
-                            tile = BEAM_STOP_D;
-                        }
-                        if BEAM_H == beam_left ||
-                           BEAM_SPLIT_X == beam_left ||
-                           BEAM_SPLIT_TL == beam_left ||
-                           BEAM_SPLIT_BL == beam_left ||
-                           BEAM_SPLIT_U == beam_left ||
-                           BEAM_SPLIT_D == beam_left ||
-                           orig == left {
-                            tile = BEAM_STOP_L;
-                        }
-                        if BEAM_H == beam_right ||
-                           BEAM_SPLIT_X == beam_right ||
-                           BEAM_SPLIT_BR == beam_right ||
-                           BEAM_SPLIT_TR == beam_right ||
-                           BEAM_SPLIT_U == beam_right ||
-                           BEAM_SPLIT_D == beam_right ||
-                           orig == right {
-                            tile = BEAM_STOP_R;
-                        }
-
-                    // orb
-                    } else if ORB == clipped {
-                        if BEAM_V == beam_up || BEAM_V == beam_down || BEAM_H == beam_left || BEAM_H == beam_right ||
-                            BEAM_SPLIT_BL == beam_left || BEAM_SPLIT_BL == beam_down ||
-                            BEAM_SPLIT_BR == beam_right || BEAM_SPLIT_BR == beam_down ||
-                            BEAM_SPLIT_TL == beam_left || BEAM_SPLIT_TL == beam_up ||
-                            BEAM_SPLIT_TR == beam_right || BEAM_SPLIT_TR == beam_up ||
-                            BEAM_SPLIT_L == beam_up || BEAM_SPLIT_L == beam_down ||
-                            BEAM_SPLIT_R == beam_up || BEAM_SPLIT_R == beam_down ||
-                            BEAM_SPLIT_U == beam_left || BEAM_SPLIT_U == beam_right ||
-                            BEAM_SPLIT_D == beam_left || BEAM_SPLIT_D == beam_right ||
-                            BEAM_SPLIT_X == beam_left || BEAM_SPLIT_X == beam_right || BEAM_SPLIT_X == beam_up || BEAM_SPLIT_X == beam_down ||
-                            orig == left || orig == right || orig == up || orig == down {
-                            tile = ORB_ACTIVE;
-                        }
-
-                    // reflectors
-                    } else if REFLECTOR_BL == clipped {
-                        if BEAM_V == beam_up || BEAM_H == beam_right ||
-                            BEAM_SPLIT_BR == beam_right ||
-                            BEAM_SPLIT_TR == beam_right ||
-                            BEAM_SPLIT_TR == beam_up ||
-                            BEAM_SPLIT_TL == beam_up ||
-                            BEAM_SPLIT_D == beam_right ||
-                            BEAM_SPLIT_U == beam_right ||
-                            BEAM_SPLIT_L == beam_up ||
-                            BEAM_SPLIT_R == beam_up ||
-                            BEAM_SPLIT_X == beam_up || BEAM_SPLIT_X == beam_right ||
-                            orig == up || orig == right {
-                            tile = BEAM_SPLIT_BL;
-                        }
-                    
-                    } else if REFLECTOR_BR == clipped {
-                        if BEAM_V == beam_up || BEAM_H == beam_left ||
-                            BEAM_SPLIT_BL == beam_left ||
-                            BEAM_SPLIT_TL == beam_left ||
-                            BEAM_SPLIT_TR == beam_up ||
-                            BEAM_SPLIT_TL == beam_up ||
-                            BEAM_SPLIT_D == beam_left ||
-                            BEAM_SPLIT_U == beam_left ||
-                            BEAM_SPLIT_L == beam_up ||
-                            BEAM_SPLIT_R == beam_up ||
-                            BEAM_SPLIT_X == beam_up || BEAM_SPLIT_X == beam_left ||
-                            orig == up || orig == left {
-                            tile = BEAM_SPLIT_BR;
-                        }
-                    
-                    } else if REFLECTOR_TL == clipped {
-                        if BEAM_V == beam_down || BEAM_H == beam_right ||
-                            BEAM_SPLIT_BR == beam_right ||
-                            BEAM_SPLIT_TR == beam_right ||
-                            BEAM_SPLIT_BL == beam_down ||
-                            BEAM_SPLIT_BR == beam_down ||
-                            BEAM_SPLIT_D == beam_right ||
-                            BEAM_SPLIT_U == beam_right ||
-                            BEAM_SPLIT_L == beam_down ||
-                            BEAM_SPLIT_R == beam_down ||
-                            BEAM_SPLIT_X == beam_down || BEAM_SPLIT_X == beam_right ||
-                            orig == down || orig == right {
-                            tile = BEAM_SPLIT_TL;
-                        }
-                    
-                    } else if REFLECTOR_TR == clipped {
-                        if BEAM_V == beam_down || BEAM_H == beam_left ||
-                            BEAM_SPLIT_BL == beam_left ||
-                            BEAM_SPLIT_TL == beam_left ||
-                            BEAM_SPLIT_BR == beam_down ||
-                            BEAM_SPLIT_BL == beam_down ||
-                            BEAM_SPLIT_D == beam_left ||
-                            BEAM_SPLIT_U == beam_left ||
-                            BEAM_SPLIT_L == beam_down ||
-                            BEAM_SPLIT_R == beam_down ||
-                            BEAM_SPLIT_X == beam_down || BEAM_SPLIT_X == beam_left ||
-                            orig == down || orig == left {
-                            tile = BEAM_SPLIT_TR;
-                        }
-                    
-                    } else if REFLECTOR_D == clipped {
-                        if BEAM_V == beam_up ||
-                           BEAM_SPLIT_TR == beam_up ||
-                           BEAM_SPLIT_TL == beam_up ||
-                           BEAM_SPLIT_L == beam_up ||
-                           BEAM_SPLIT_R == beam_up ||
-                           BEAM_SPLIT_X == beam_up ||
-                           orig == up {
-                            tile = BEAM_SPLIT_D;
-                        }
-                    
-                    } else if REFLECTOR_L == clipped {
-                        if BEAM_H == beam_right ||
-                           BEAM_SPLIT_BR == beam_right ||
-                           BEAM_SPLIT_TR == beam_right ||
-                           BEAM_SPLIT_U == beam_right ||
-                           BEAM_SPLIT_D == beam_right ||
-                           BEAM_SPLIT_X == beam_right ||
-                           orig == right {
-                            tile = BEAM_SPLIT_L;
-                        }
-                    
-                    } else if REFLECTOR_U == clipped {
-                        if BEAM_V == beam_down ||
-                           BEAM_SPLIT_BR == beam_down ||
-                           BEAM_SPLIT_BL == beam_down ||
-                           BEAM_SPLIT_L == beam_down ||
-                           BEAM_SPLIT_R == beam_down ||
-                           BEAM_SPLIT_X == beam_down ||
-                           orig == down {
-                            tile = BEAM_SPLIT_U;
-                        }
-                
-                    } else if REFLECTOR_R == clipped {
-                        if BEAM_H == beam_left ||
-                           BEAM_SPLIT_BL == beam_left ||
-                           BEAM_SPLIT_TL == beam_left ||
-                           BEAM_SPLIT_U == beam_left ||
-                           BEAM_SPLIT_D == beam_left ||
-                           BEAM_SPLIT_X == beam_left ||
-                           orig == left {
-                            tile = BEAM_SPLIT_R;
-                        }
-    
-                    }
-
-                    if 255 != tile {
-                        heap.scene_data.beams[idx] = tile;
-                        keep_going = true;
-                        break 'outer;
-                    }
-                }
-            }
-
-            //if !keep_going { break; }
-        //}
+        // reveal one more cell of the traced beam
+        let step = cache.ui_data.beam_step as usize;
+        if step < heap.beam_trace.path.len() {
+            let (idx, tile) = heap.beam_trace.path[step];
+            heap.scene_data.beams[idx] = tile;
+            cache.ui_data.beam_step += 1;
+        }
 
         if !cache.ui_data.beam_hold {
             cache.ui_data.beam_alpha -= 0.05;
@@ -590,7 +513,7 @@ pub fn update(cache: &mut game::GameData, heap: &mut game::GameDataHeap, world:
         }
     }
 
-    world.entity_set_tilemap(cache.beam_ent, cache.tileset_ent, sz, &heap.scene_data.beams);
+    world.entity_set_tilemap(cache.beam_ent, cache.tileset_ent, sz, &beam::tinted_beams(&heap.scene_data.beams, &heap.beam_trace.colors));
     world.entity_set_visibility(cache.beam_ent, false);
     if 1.0e-6 < cache.ui_data.beam_alpha {
         world.entity_set_visibility(cache.beam_ent, true);
@@ -605,6 +528,11 @@ pub fn update(cache: &mut game::GameData, heap: &mut game::GameDataHeap, world:
 
 pub fn click(cache: &mut game::GameData, heap: &mut game::GameDataHeap, world: &mut mgfw::ecs::World) -> bool {
 
+    // a drag ends where the mouse lets go
+    if cache.ui_data.dragging {
+        return drop_drag(cache, heap, world);
+    }
+
     if cache.ui_data.click_delay > 0 { return false; }
 
     let mx = world.mouse_x;
@@ -615,17 +543,42 @@ pub fn click(cache: &mut game::GameData, heap: &mut game::GameDataHeap, world: &
         if TRANSITION_MODE_INVALID == cache.ui_data.transition_mode {
             let xx = SCREEN_XRES_HALF as i32 - 8;
             let yy = 150;
-            if mx >= xx && mx < xx + 16 && my >= yy && my < yy + 16 {
+            let start = mx >= xx && mx < xx + 16 && my >= yy && my < yy + 16;
+            let daily = hover_text(world, cache.daily_ent, 188);
+            let resume = game_state::continue_text(heap).is_some() && hover_text(world, cache.continue_ent, 202);
+            if start || daily || resume {
                 transition(cache, heap, world);
                 cache.ui_data.click_delay = 30;
+                if daily {
+                    cache.player_data.daily = heap.today;
+                } else if resume {
+                    game_state::continue_level(cache, heap);
+                } else if heap.speedrun.enabled {
+                    speedrun::start(&mut heap.speedrun);
+                }
 
                 world.entity_set_text(cache.copyright_ent, String::from("http://mirrorb.io"));
                 world.entity_set_position_xy(cache.copyright_ent, SCREEN_XRES_HALF as f32 - (world.text_get_width(cache.copyright_ent) as f32 * 0.5).floor(), SCREEN_YRES as f32 - 28.0);
             }
+            if hover_text(world, cache.speedrun_ent, 174) {
+                heap.speedrun.enabled = !heap.speedrun.enabled;
+                let text = if heap.speedrun.enabled { "Speedrun: on" } else { "Speedrun: off" };
+                world.entity_set_text(cache.speedrun_ent, String::from(text));
+                world.entity_set_position_xy(cache.speedrun_ent, SCREEN_XRES_HALF as f32 - (world.text_get_width(cache.speedrun_ent) as f32 * 0.5).floor(), 174.0);
+            }
+            if hover_text(world, cache.select_ent, 216) {
+                open_select(cache, heap, world);
+                cache.ui_data.click_delay = 30;
+            }
         }
         return true;
     }
 
+    if MENU_SELECT == cache.ui_data.menu {
+        click_select(cache, heap, world);
+        return true;
+    }
+
     // game input
     
     let mut consumed = false;
@@ -636,41 +589,24 @@ pub fn click(cache: &mut game::GameData, heap: &mut game::GameDataHeap, world: &
         // check for inventory item pickup
         if INVENTORY_IDX_INVALID != cache.ui_data.inventory_idx {
             cache.ui_data.holding_idx = cache.ui_data.inventory_idx;
+            cache.ui_data.holding_from = BOARD_IDX_INVALID;
             consumed = true;
 
         } else if BOARD_IDX_INVALID != cache.ui_data.board_idx  {
 
             // if holding, place piece
             if HOLDING_INVALID != cache.ui_data.holding_idx {
-                
-                // is board location empty?
-                if 0 == heap.scene_data.reflectors[cache.ui_data.board_idx as usize] {
-                    game_state::place_piece(cache, heap, cache.ui_data.holding_idx, cache.ui_data.board_idx);
-                    cache.ui_data.holding_idx = HOLDING_INVALID;
-                    consumed = true;
-                
-                // else swap with piece on board
-                } else {
+                consumed = put_down(cache, heap, true);
 
-                    // find piece on this board location
-                    for i in 0..npcs {
-                        if cache.ui_data.board_idx == cache.pieces[i].board_idx {
-                            game_state::swap_piece(cache, heap, cache.ui_data.holding_idx, cache.ui_data.board_idx, i);
-                            cache.ui_data.holding_idx = i as u8;
-                            consumed = true;
-                            break;
-                        }
-                    }
-
-                }
-            
             // else check for piece pickup
             } else {
-                // find piece on this board location
+                // find piece on this board location, locked ones stay put
                 for i in 0..npcs {
                     if cache.ui_data.board_idx == cache.pieces[i].board_idx {
-                        game_state::pickup_piece(cache, heap, i);
-                        cache.ui_data.holding_idx = i as u8;
+                        if game_state::pickup_piece(cache, heap, i) {
+                            cache.ui_data.holding_idx = i as u8;
+                            cache.ui_data.holding_from = cache.ui_data.board_idx;
+                        }
                         consumed = true;
                         break;
                     }
@@ -679,16 +615,13 @@ pub fn click(cache: &mut game::GameData, heap: &mut game::GameDataHeap, world: &
 
         // check for reset click
         } else if HOLDING_INVALID == cache.ui_data.holding_idx && MENU_HOVER_INVALID != cache.ui_data.menu_hover_idx {
-            match cache.ui_data.menu_hover_idx {
-                MENU_HOVER_UNDO => game_state::history_undo(cache, heap, world),
-                MENU_HOVER_REDO => game_state::history_redo(cache, heap, world),
-                MENU_HOVER_ROTATE => game_state::rotate(cache, heap, world),
-                MENU_HOVER_FLIP_H => game_state::flip_h(cache, heap, world),
-                MENU_HOVER_FLIP_V => game_state::flip_v(cache, heap, world),
-                MENU_HOVER_RESET => game_state::reset_level(cache, heap),
-                MENU_HOVER_TRASH => game_state::trash_level(cache, heap, world),
-                _ => (),
-            }
+            menu_action(cache, heap, world, cache.ui_data.menu_hover_idx);
+            consumed = true;
+
+        // step between undo branches
+        } else if HOLDING_INVALID == cache.ui_data.holding_idx && (hover_ent(world, cache.branch_prev_ent) || hover_ent(world, cache.branch_next_ent)) {
+            let step = if hover_ent(world, cache.branch_next_ent) { 1 } else { -1 };
+            game_state::history_branch(cache, heap, world, step);
             consumed = true;
 
         } else {
@@ -709,6 +642,11 @@ pub fn click(cache: &mut game::GameData, heap: &mut game::GameDataHeap, world: &
 
     if consumed {
         cache.ui_data.click_delay = 30;
+
+        // any other action invalidates the hint
+        if MENU_HOVER_HINT != cache.ui_data.menu_hover_idx {
+            drop_hint(cache, heap);
+        }
     }
 
 
@@ -725,6 +663,427 @@ pub fn click(cache: &mut game::GameData, heap: &mut game::GameDataHeap, world: &
 }
 
 
+// f5 writes the board being played to the share file, f6 with the pieces placed so far
+pub fn export_board(cache: &mut game::GameData, heap: &game::GameDataHeap, with_placements: bool) -> bool {
+
+    if MENU_GAME != cache.ui_data.menu || TRANSITION_MODE_INVALID != cache.ui_data.transition_mode { return false; }
+
+    let code = game_state::export_board(cache, heap, with_placements);
+    println!("share code: {code}");
+    if let Err(err) = std::fs::write(share::SHARE_FILE, format!("{code}\n")) {
+        println!("{}: {err}, code not saved", share::SHARE_FILE);
+    }
+    true
+}
+
+
+// f7 plays the board in the share file instead of the current one
+pub fn import_board(cache: &mut game::GameData, heap: &mut game::GameDataHeap, world: &mut mgfw::ecs::World) -> bool {
+
+    if MENU_GAME != cache.ui_data.menu || TRANSITION_MODE_INVALID != cache.ui_data.transition_mode { return false; }
+
+    let code = match game_state::read_share(world, share::SHARE_FILE) {
+        Ok(code) => code,
+        Err(err) => {
+            println!("{}: {err}", share::SHARE_FILE);
+            return true;
+        },
+    };
+
+    let_go(cache);
+    cache.ui_data.holding_idx = HOLDING_INVALID;
+
+    if let Err(err) = game_state::import_board(cache, heap, world, &code) {
+        println!("{}: {err}", share::SHARE_FILE);
+        return true;
+    }
+
+    cache.ui_data.beam_hold = false;
+    drop_hint(cache, heap);
+    true
+}
+
+
+// a game menu button, clicked or from its key
+fn menu_action(cache: &mut game::GameData, heap: &mut game::GameDataHeap, world: &mut mgfw::ecs::World, action: u8) {
+    match action {
+        MENU_HOVER_UNDO => game_state::history_undo(cache, heap, world),
+        MENU_HOVER_REDO => game_state::history_redo(cache, heap, world),
+        MENU_HOVER_ROTATE => game_state::rotate(cache, heap, world),
+        MENU_HOVER_FLIP_H => game_state::flip_h(cache, heap, world),
+        MENU_HOVER_FLIP_V => game_state::flip_v(cache, heap, world),
+        MENU_HOVER_RESET => game_state::reset_level(cache, heap),
+        MENU_HOVER_TRASH => game_state::trash_level(cache, heap, world),
+        MENU_HOVER_HINT => game_state::request_hint(cache, heap),
+        _ => (),
+    }
+}
+
+
+fn drop_hint(cache: &mut game::GameData, heap: &mut game::GameDataHeap) {
+    cache.ui_data.hint_piece = HOLDING_INVALID;
+    cache.ui_data.hint_idx = BOARD_IDX_INVALID;
+    game_state::cancel_hint(heap);
+}
+
+
+// play by action, from the mouse, keys or a gamepad. the cursor actions move the
+// pointer between everything a mouse could click, click is the mouse button and
+// fire fires the source under the pointer. see menu_actions for the rest
+pub fn actions(cache: &mut game::GameData, heap: &mut game::GameDataHeap, world: &mut mgfw::ecs::World) -> bool {
+
+    let mut consumed = false;
+
+    // key repeat keeps the pointer moving
+    let dirs = [(ACTION_CURSOR_UP, 0, -1), (ACTION_CURSOR_DOWN, 0, 1), (ACTION_CURSOR_LEFT, -1, 0), (ACTION_CURSOR_RIGHT, 1, 0)];
+    for (action, dx, dy) in dirs {
+        if !world.input.repeated(action) { continue; }
+        if TRANSITION_MODE_INVALID == cache.ui_data.transition_mode {
+            move_pointer(cache, heap, world, dx, dy);
+        }
+        consumed = true;
+    }
+
+    if world.input.pressed(ACTION_CLICK) {
+        consumed |= click_down(cache, heap, world);
+    }
+    if world.input.released(ACTION_CLICK) {
+        consumed |= click(cache, heap, world);
+    }
+    if world.input.released(ACTION_RETURN_PIECE) {
+        consumed |= return_piece(cache, heap);
+    }
+
+    // fire, and on to the next level once it is won. menus take it as a click
+    if world.input.pressed(ACTION_FIRE) {
+        if MENU_GAME == cache.ui_data.menu && BOARD_IDX_INVALID != cache.ui_data.source_hover {
+            click_down(cache, heap, world);
+        }
+        consumed = true;
+    }
+    if world.input.released(ACTION_FIRE) {
+        if MENU_GAME != cache.ui_data.menu || cache.ui_data.beam_hold {
+            click(cache, heap, world);
+        } else if cache.level_complete && !cache.final_level && TRANSITION_MODE_INVALID == cache.ui_data.transition_mode && 0 == cache.ui_data.click_delay {
+            game_state::next_level(cache, heap, world);
+            cache.ui_data.click_delay = 30;
+        }
+        consumed = true;
+    }
+
+    consumed | menu_actions(cache, heap, world)
+}
+
+
+// put the piece in hand down on the hovered board cell, or swap it with the piece
+// there. the swapped piece comes into the hand, or goes back to its inventory slot
+// when the hand is to end up empty
+fn put_down(cache: &mut game::GameData, heap: &mut game::GameDataHeap, hold_swapped: bool) -> bool {
+
+    let board_idx = cache.ui_data.board_idx;
+    let npcs = heap.scene_data.num_pcs as usize;
+
+    // is board location empty?
+    if 0 == heap.scene_data.reflectors[board_idx as usize] {
+        game_state::place_piece(cache, heap, cache.ui_data.holding_idx, board_idx);
+        cache.ui_data.holding_idx = HOLDING_INVALID;
+        return true;
+    }
+
+    // else swap with piece on board, locked ones stay put
+    if let Some(i) = (0..npcs).find(|i| board_idx == cache.pieces[*i].board_idx) {
+        if game_state::swap_piece(cache, heap, cache.ui_data.holding_idx, board_idx, i) {
+            cache.ui_data.holding_idx = if hold_swapped { i as u8 } else { HOLDING_INVALID };
+            cache.ui_data.holding_from = board_idx;
+        }
+        return true;
+    }
+
+    false
+}
+
+
+// a press on a piece in the inventory or on the board picks it up and starts a drag.
+// a board piece is only lifted, what the drag did is recorded once it is dropped
+fn start_drag(cache: &mut game::GameData, heap: &mut game::GameDataHeap, world: &mut mgfw::ecs::World) -> bool {
+
+    let npcs = heap.scene_data.num_pcs as usize;
+
+    if INVENTORY_IDX_INVALID != cache.ui_data.inventory_idx {
+        cache.ui_data.holding_idx = cache.ui_data.inventory_idx;
+        cache.ui_data.holding_from = BOARD_IDX_INVALID;
+
+    } else if BOARD_IDX_INVALID != cache.ui_data.board_idx {
+        let board_idx = cache.ui_data.board_idx;
+        match (0..npcs).find(|i| board_idx == cache.pieces[*i].board_idx) {
+            Some(i) if game_state::lift_piece(cache, i) => {
+                cache.ui_data.holding_idx = i as u8;
+                cache.ui_data.holding_from = board_idx;
+            },
+            _ => return false,
+        }
+
+    } else {
+        return false;
+    }
+
+    cache.ui_data.dragging = true;
+    cache.ui_data.drag_x = world.mouse_x;
+    cache.ui_data.drag_y = world.mouse_y;
+    drop_hint(cache, heap);
+    scene::update_clipping(cache, heap);
+    true
+}
+
+
+// let go of a dragged piece. let go where it was pressed it is a plain click and
+// the piece stays in hand for the next click to place. dropped on a floor cell it
+// is put down, back on its own cell nothing is recorded, anywhere else it goes
+// back to its inventory slot
+fn drop_drag(cache: &mut game::GameData, heap: &mut game::GameDataHeap, world: &mut mgfw::ecs::World) -> bool {
+
+    cache.ui_data.dragging = false;
+
+    // undo and friends already took the piece out of hand
+    if HOLDING_INVALID == cache.ui_data.holding_idx { return false; }
+
+    // a lifted piece goes back first so the move is recorded from its cell
+    let piece = cache.ui_data.holding_idx as usize;
+    let from = cache.ui_data.holding_from;
+    let lifted = BOARD_IDX_INVALID != from;
+    if lifted {
+        game_state::unlift_piece(cache, piece, from);
+    }
+
+    let moved = 4 < (world.mouse_x - cache.ui_data.drag_x).abs() + (world.mouse_y - cache.ui_data.drag_y).abs();
+    if moved {
+        let board_idx = cache.ui_data.board_idx;
+        if lifted && from == board_idx {
+            // dropped back on its own cell
+        } else if !(BOARD_IDX_INVALID != board_idx && put_down(cache, heap, false)) && lifted {
+            game_state::pickup_piece(cache, heap, piece);
+        }
+        cache.ui_data.holding_idx = HOLDING_INVALID;
+
+    } else if lifted {
+        game_state::pickup_piece(cache, heap, piece);
+    }
+
+    cache.ui_data.click_delay = 30;
+    scene::update_clipping(cache, heap);
+    true
+}
+
+
+// a piece lifted by a drag is still on its cell as far as the history knows,
+// put it back there before the hand is emptied
+pub fn let_go(cache: &mut game::GameData) {
+
+    if cache.ui_data.dragging && BOARD_IDX_INVALID != cache.ui_data.holding_from && HOLDING_INVALID != cache.ui_data.holding_idx {
+        game_state::unlift_piece(cache, cache.ui_data.holding_idx as usize, cache.ui_data.holding_from);
+    }
+    cache.ui_data.dragging = false;
+}
+
+
+// the right button. the piece in hand goes back to the cell it was picked up
+// from, or to its inventory slot when that cell has been taken. a placed piece
+// goes straight back to its inventory slot
+fn return_piece(cache: &mut game::GameData, heap: &mut game::GameDataHeap) -> bool {
+
+    if MENU_GAME != cache.ui_data.menu || TRANSITION_MODE_INVALID != cache.ui_data.transition_mode || cache.level_complete { return false; }
+    if cache.ui_data.click_delay > 0 { return false; }
+
+    let npcs = heap.scene_data.num_pcs as usize;
+
+    let_go(cache);
+
+    if HOLDING_INVALID != cache.ui_data.holding_idx {
+        let from = cache.ui_data.holding_from;
+        if BOARD_IDX_INVALID != from && cache.pieces[..npcs].iter().all(|p| from != p.board_idx) {
+            game_state::place_piece(cache, heap, cache.ui_data.holding_idx, from);
+        }
+        cache.ui_data.holding_idx = HOLDING_INVALID;
+
+    } else if BOARD_IDX_INVALID != cache.ui_data.board_idx {
+        let board_idx = cache.ui_data.board_idx;
+        match (0..npcs).find(|i| board_idx == cache.pieces[*i].board_idx) {
+            Some(i) if game_state::pickup_piece(cache, heap, i) => (),
+            _ => return false,
+        }
+
+    } else {
+        return false;
+    }
+
+    cache.ui_data.click_delay = 30;
+    drop_hint(cache, heap);
+    scene::update_clipping(cache, heap);
+    true
+}
+
+
+// the game menu buttons, and stepping between undo branches
+fn menu_actions(cache: &mut game::GameData, heap: &mut game::GameDataHeap, world: &mut mgfw::ecs::World) -> bool {
+
+    if MENU_GAME != cache.ui_data.menu || TRANSITION_MODE_INVALID != cache.ui_data.transition_mode || cache.level_complete { return false; }
+
+    let buttons = [
+        (ACTION_UNDO, MENU_HOVER_UNDO),
+        (ACTION_REDO, MENU_HOVER_REDO),
+        (ACTION_ROTATE, MENU_HOVER_ROTATE),
+        (ACTION_FLIP_H, MENU_HOVER_FLIP_H),
+        (ACTION_FLIP_V, MENU_HOVER_FLIP_V),
+        (ACTION_RESET, MENU_HOVER_RESET),
+        (ACTION_NEW_BOARD, MENU_HOVER_TRASH),
+        (ACTION_HINT, MENU_HOVER_HINT),
+        (ACTION_BRANCH_PREV, MENU_HOVER_INVALID),
+        (ACTION_BRANCH_NEXT, MENU_HOVER_INVALID),
+    ];
+    let (action, button) = match buttons.iter().find(|(a, _)| world.input.pressed(*a)) {
+        Some(b) => *b,
+        None => return false,
+    };
+
+    // a piece in hand goes back first, the board is about to change under it
+    let_go(cache);
+    cache.ui_data.holding_idx = HOLDING_INVALID;
+
+    match button {
+        MENU_HOVER_INVALID => {
+            let step = if ACTION_BRANCH_NEXT == action { 1 } else { -1 };
+            game_state::history_branch(cache, heap, world, step);
+        },
+        _ => menu_action(cache, heap, world, button),
+    }
+
+    if MENU_HOVER_HINT != button {
+        drop_hint(cache, heap);
+    }
+    scene::update_clipping(cache, heap);
+    true
+}
+
+
+// step the pointer to the nearest target in that direction. a pointer that is
+// not on any target, say where the mouse left it, goes to the nearest one
+fn move_pointer(cache: &mut game::GameData, heap: &game::GameDataHeap, world: &mut mgfw::ecs::World, dx: i32, dy: i32) {
+
+    let mx = world.mouse_x;
+    let my = world.mouse_y;
+
+    let targets = pointer_targets(cache, heap, world);
+    let distance = |t: &(i32, i32)| (t.0 - mx).abs() + (t.1 - my).abs();
+    let on_target = targets.iter().any(|t| 4 >= distance(t));
+
+    let mut best = None;
+    let mut best_score = i32::MAX;
+    for t in targets.iter() {
+        let score = if on_target {
+            // straight ahead beats off to the side
+            let along = (t.0 - mx) * dx + (t.1 - my) * dy;
+            let across = ((t.0 - mx) * dy - (t.1 - my) * dx).abs();
+            if 0 >= along { continue; }
+            along + 2 * across
+        } else {
+            distance(t)
+        };
+        if score < best_score {
+            best_score = score;
+            best = Some(*t);
+        }
+    }
+
+    if let Some((xx, yy)) = best {
+        world.mouse_x = xx;
+        world.mouse_y = yy;
+        cache.ui_data.pointer_keys = true;
+        cache.ui_data.pointer_x = xx;
+        cache.ui_data.pointer_y = yy;
+    }
+}
+
+
+// the centre of everything on screen the mouse could click
+fn pointer_targets(cache: &game::GameData, heap: &game::GameDataHeap, world: &mut mgfw::ecs::World) -> Vec<(i32, i32)> {
+
+    let mut ret = Vec::new();
+
+    let mut texts = Vec::new();
+    if MENU_MAIN == cache.ui_data.menu {
+        ret.push((SCREEN_XRES_HALF as i32, 158));
+        texts.extend([cache.speedrun_ent, cache.daily_ent, cache.continue_ent, cache.select_ent]);
+
+    } else if MENU_SELECT == cache.ui_data.menu {
+        texts.extend(cache.select_ents);
+        texts.extend([cache.select_prev_ent, cache.select_next_ent, cache.select_back_ent]);
+
+    } else if MENU_GAME == cache.ui_data.menu {
+        let sz = heap.scene_data.sz;
+        let cell = board_cell(sz) as i32;
+        let left = cache.ui_data.board_left + cell / 2;
+        let top = cache.ui_data.board_top + cell / 2;
+
+        // every board cell but the corners, the edges hold the sources
+        for yy in 0..sz {
+            for xx in 0..sz {
+                if (0 == xx || sz-1 == xx) && (0 == yy || sz-1 == yy) { continue; }
+                ret.push((left + xx as i32 * cell, top + yy as i32 * cell));
+            }
+        }
+
+        // game menu, then the row below it and the inventory
+        let menu_left = cache.ui_data.menu_left;
+        let menu_top = cache.ui_data.menu_top;
+        for i in 0..9 {
+            ret.push((menu_left + 5 + (i % 3) * 18 + 9, menu_top + (i / 3) * 18 + if 6 <= i { 18 } else { 9 }));
+        }
+        for i in 0..heap.scene_data.num_pcs as i32 {
+            ret.push((menu_left + (i % 4) * 16 + 8, menu_top + 4 * 18 + 2 + (i / 4) * 16 + 8));
+        }
+
+        texts.extend([cache.branch_prev_ent, cache.branch_next_ent]);
+    }
+
+    for ent in texts {
+        if !world.entity_is_visible(ent) { continue; }
+        let pos = world.entity_get_position(ent);
+        ret.push((pos.x as i32 + world.text_get_width(ent) as i32 / 2, pos.y as i32 + 6));
+    }
+
+    ret
+}
+
+
+// frame the board cell under a key driven pointer, held pieces and sources show themselves
+fn update_pointer(cache: &mut game::GameData, heap: &game::GameDataHeap, world: &mut mgfw::ecs::World) {
+
+    let mx = world.mouse_x;
+    let my = world.mouse_y;
+
+    // the mouse took over
+    if mx != cache.ui_data.pointer_x || my != cache.ui_data.pointer_y {
+        cache.ui_data.pointer_keys = false;
+    }
+
+    let sz = heap.scene_data.sz as i32;
+    let cell = board_cell(heap.scene_data.sz) as i32;
+    let xx = (mx - cache.ui_data.board_left).div_euclid(cell);
+    let yy = (my - cache.ui_data.board_top).div_euclid(cell);
+
+    let visible = cache.ui_data.pointer_keys && !cache.level_complete &&
+        HOLDING_INVALID == cache.ui_data.holding_idx && BOARD_IDX_INVALID == cache.ui_data.source_hover &&
+        0 <= xx && xx < sz && 0 <= yy && yy < sz;
+
+    world.entity_set_visibility(cache.cursor_ent, visible);
+    if !visible { return; }
+
+    world.entity_set_position_xy(cache.cursor_ent, (cache.ui_data.board_left + xx * cell) as f32, (cache.ui_data.board_top + yy * cell) as f32);
+    world.entity_set_scale_xy(cache.cursor_ent, cell as f32, cell as f32);
+    world.entity_set_tilemap(cache.cursor_ent, cache.tileset_ent, 1, &vec![GRID]);
+}
+
+
 pub fn click_down(cache: &mut game::GameData, heap: &mut game::GameDataHeap, world: &mut mgfw::ecs::World) -> bool {
 
     if cache.ui_data.click_delay > 0 { return false; }
@@ -744,19 +1103,34 @@ pub fn click_down(cache: &mut game::GameData, heap: &mut game::GameDataHeap, wor
         // reset beams
         heap.scene_data.beams = vec![0; sz2];
 
+        let npcs = heap.scene_data.num_pcs as usize;
+        heap.beam_trace = beam::trace(&heap.scene_data, &cache.pieces[0..npcs], cache.ui_data.beam_origin as usize);
+        cache.ui_data.beam_step = 0;
+        cache.player_data.moves += 1;
+
         consumed = true;
     
+    } else if MENU_GAME == cache.ui_data.menu && HOLDING_INVALID == cache.ui_data.holding_idx {
+        consumed = start_drag(cache, heap, world);
     }
 
     consumed
 }
 
 
-fn update_main(cache: &mut game::GameData, heap: &mut game::GameDataHeap, world: &mut mgfw::ecs::World) {
+fn update_main(cache: &mut game::GameData, world: &mut mgfw::ecs::World) {
 
     let mx = world.mouse_x;
     let my = world.mouse_y;
 
+    for (ent, yy) in [(cache.speedrun_ent, 174), (cache.daily_ent, 188), (cache.continue_ent, 202), (cache.select_ent, 216)] {
+        if hover_text(world, ent, yy) {
+            world.entity_set_color_rgba(ent, 1.0, 1.0, 1.0, 1.0);
+        } else {
+            world.entity_set_color_rgba(ent, 0.5, 0.6, 0.7, 1.0);
+        }
+    }
+
     let xx = SCREEN_XRES_HALF as i32 - 8;
     let yy = 150;
 
@@ -780,11 +1154,256 @@ fn update_main(cache: &mut game::GameData, heap: &mut game::GameDataHeap, world:
 }
 
 
+// main menu text entries are centered, one line tall
+fn hover_text(world: &mgfw::ecs::World, ent: usize, yy: i32) -> bool {
+    let w = world.text_get_width(ent) as i32;
+    let xx = SCREEN_XRES_HALF as i32 - w / 2;
+    world.mouse_x >= xx && world.mouse_x < xx + w && world.mouse_y >= yy && world.mouse_y < yy + 12
+}
+
+
+// any text entity, left aligned at its position, one line tall
+fn hover_ent(world: &mut mgfw::ecs::World, ent: usize) -> bool {
+    if !world.entity_is_visible(ent) { return false; }
+    let pos = world.entity_get_position(ent);
+    let w = world.text_get_width(ent) as i32;
+    let xx = pos.x as i32;
+    let yy = pos.y as i32;
+    world.mouse_x >= xx && world.mouse_x < xx + w && world.mouse_y >= yy && world.mouse_y < yy + 12
+}
+
+
+fn show_main(cache: &mut game::GameData, heap: &game::GameDataHeap, world: &mut mgfw::ecs::World, visible: bool) {
+    world.entity_set_visibility(cache.logo_ent, visible);
+    world.entity_set_visibility(cache.start_ent, visible);
+    world.entity_set_visibility(cache.speedrun_ent, visible);
+    world.entity_set_visibility(cache.daily_ent, visible);
+    world.entity_set_visibility(cache.continue_ent, visible && game_state::continue_text(heap).is_some());
+    world.entity_set_visibility(cache.select_ent, visible);
+}
+
+
+fn show_select(cache: &mut game::GameData, world: &mut mgfw::ecs::World, visible: bool) {
+    world.entity_set_visibility(cache.select_title_ent, visible);
+    world.entity_set_visibility(cache.select_prev_ent, visible);
+    world.entity_set_visibility(cache.select_next_ent, visible);
+    world.entity_set_visibility(cache.select_back_ent, visible);
+    for i in 0..SELECT_GROUPS * 2 {
+        world.entity_set_visibility(cache.select_ents[i], visible);
+    }
+}
+
+
+fn open_select(cache: &mut game::GameData, heap: &mut game::GameDataHeap, world: &mut mgfw::ecs::World) {
+    cache.ui_data.menu = MENU_SELECT;
+    show_main(cache, heap, world, false);
+    show_select(cache, world, true);
+    show_select_page(cache, heap, world);
+}
+
+
+// level and sub-level of a level select entry on the current page, if it has one
+fn select_entry(cache: &game::GameData, heap: &game::GameDataHeap, i: usize) -> Option<(usize, usize)> {
+    let level = cache.ui_data.select_page * SELECT_GROUPS + i / 2 + 1;
+    let sub_level = i % 2;
+    if level > game_state::num_levels(heap) || sub_level >= game_state::num_sub_levels(heap, level) {
+        return None;
+    }
+    Some((level, sub_level))
+}
+
+
+fn show_select_page(cache: &mut game::GameData, heap: &game::GameDataHeap, world: &mut mgfw::ecs::World) {
+
+    let page = cache.ui_data.select_page;
+    let npages = game_state::num_levels(heap).div_ceil(SELECT_GROUPS);
+
+    world.entity_set_text(cache.select_title_ent, format!("Select level   {}/{}", page + 1, npages));
+    world.entity_set_position_xy(cache.select_title_ent, SCREEN_XRES_HALF as f32 - (world.text_get_width(cache.select_title_ent) as f32 * 0.5).floor(), 20.0);
+    world.entity_set_visibility(cache.select_prev_ent, 0 < page);
+    world.entity_set_visibility(cache.select_next_ent, page + 1 < npages);
+
+    // won levels show their best stars, older progress files can hold 0 for a win
+    for i in 0..SELECT_GROUPS * 2 {
+        let ent = cache.select_ents[i];
+        let (level, sub_level) = match select_entry(cache, heap, i) {
+            Some(entry) => entry,
+            None => {
+                world.entity_set_visibility(ent, false);
+                continue;
+            },
+        };
+        let d = game_state::level_displayed(level, sub_level);
+        let mut text = format!("{d}");
+        if progress::is_completed(&heap.progress, d) {
+            text = format!("{d} {}", "*".repeat(heap.progress.stars[d - 1].max(1)));
+        }
+        world.entity_set_text(ent, text);
+        world.entity_set_visibility(ent, true);
+    }
+}
+
+
+fn update_select(cache: &mut game::GameData, heap: &mut game::GameDataHeap, world: &mut mgfw::ecs::World) {
+
+    for ent in [cache.select_prev_ent, cache.select_next_ent, cache.select_back_ent] {
+        match hover_ent(world, ent) {
+            true => world.entity_set_color_rgba(ent, 1.0, 1.0, 1.0, 1.0),
+            false => world.entity_set_color_rgba(ent, 0.5, 0.6, 0.7, 1.0),
+        }
+    }
+
+    // locked entries are dimmed, won ones are green
+    for i in 0..SELECT_GROUPS * 2 {
+        let ent = cache.select_ents[i];
+        let (level, sub_level) = match select_entry(cache, heap, i) {
+            Some(entry) => entry,
+            None => continue,
+        };
+        if !game_state::level_unlocked(heap, level, sub_level) {
+            world.entity_set_color_rgba(ent, 0.25, 0.3, 0.35, 1.0);
+        } else if hover_ent(world, ent) {
+            world.entity_set_color_rgba(ent, 1.0, 1.0, 1.0, 1.0);
+        } else if progress::is_completed(&heap.progress, game_state::level_displayed(level, sub_level)) {
+            world.entity_set_color_rgba(ent, 0.4, 0.9, 0.5, 1.0);
+        } else {
+            world.entity_set_color_rgba(ent, 0.5, 0.6, 0.7, 1.0);
+        }
+    }
+}
+
+
+fn click_select(cache: &mut game::GameData, heap: &mut game::GameDataHeap, world: &mut mgfw::ecs::World) {
+
+    if hover_ent(world, cache.select_back_ent) {
+        cache.ui_data.menu = MENU_MAIN;
+        show_select(cache, world, false);
+        show_main(cache, heap, world, true);
+        cache.ui_data.click_delay = 30;
+        return;
+    }
+
+    if hover_ent(world, cache.select_prev_ent) || hover_ent(world, cache.select_next_ent) {
+        match hover_ent(world, cache.select_prev_ent) {
+            true => cache.ui_data.select_page -= 1,
+            false => cache.ui_data.select_page += 1,
+        }
+        show_select_page(cache, heap, world);
+        cache.ui_data.click_delay = 30;
+        return;
+    }
+
+    for i in 0..SELECT_GROUPS * 2 {
+        let (level, sub_level) = match select_entry(cache, heap, i) {
+            Some(entry) => entry,
+            None => continue,
+        };
+        if !hover_ent(world, cache.select_ents[i]) || !game_state::level_unlocked(heap, level, sub_level) { continue; }
+
+        // same way in as the start button, next_level picks up the selection
+        game_state::select_level(cache, heap, world, level, sub_level);
+        show_select(cache, world, false);
+        cache.ui_data.menu = MENU_MAIN;
+        transition(cache, heap, world);
+        cache.ui_data.click_delay = 30;
+        world.entity_set_text(cache.copyright_ent, String::from("http://mirrorb.io"));
+        world.entity_set_position_xy(cache.copyright_ent, SCREEN_XRES_HALF as f32 - (world.text_get_width(cache.copyright_ent) as f32 * 0.5).floor(), SCREEN_YRES as f32 - 28.0);
+        return;
+    }
+}
+
+
+// branch k/n with arrows to step between the branches of the last fork
+fn update_branch(cache: &mut game::GameData, heap: &game::GameDataHeap, world: &mut mgfw::ecs::World) {
+
+    let branch = if cache.level_complete { None } else { game_state::history_branch_info(cache, heap) };
+
+    world.entity_set_visibility(cache.branch_ent, branch.is_some());
+    world.entity_set_visibility(cache.branch_prev_ent, branch.is_some());
+    world.entity_set_visibility(cache.branch_next_ent, branch.is_some());
+
+    let (k, n) = match branch {
+        Some(branch) => branch,
+        None => return,
+    };
+
+    let xx = cache.ui_data.menu_left as f32;
+    let yy = cache.ui_data.menu_top as f32 + 4.0 * 18.0 + 6.0 + 16.0 * (heap.scene_data.num_pcs as usize).div_ceil(4) as f32;
+
+    world.entity_set_text(cache.branch_ent, format!("Branch {}/{n}", k + 1));
+    world.entity_set_position_xy(cache.branch_prev_ent, xx, yy);
+    world.entity_set_position_xy(cache.branch_ent, xx + 10.0, yy);
+    world.entity_set_position_xy(cache.branch_next_ent, xx + 14.0 + world.text_get_width(cache.branch_ent) as f32, yy);
+
+    for ent in [cache.branch_prev_ent, cache.branch_next_ent] {
+        match HOLDING_INVALID == cache.ui_data.holding_idx && hover_ent(world, ent) {
+            true => world.entity_set_color_rgba(ent, 1.0, 1.0, 1.0, 1.0),
+            false => world.entity_set_color_rgba(ent, 0.5, 0.6, 0.7, 1.0),
+        }
+    }
+}
+
+
+// top right, what it takes to rebuild the current board
+fn update_debug(cache: &mut game::GameData, world: &mut mgfw::ecs::World) {
+
+    world.entity_set_visibility(cache.debug_ent, cache.debug_overlay);
+    if !cache.debug_overlay { return; }
+
+    world.entity_set_text(cache.debug_ent, game_state::debug_text(cache, world));
+    let w = world.text_get_width(cache.debug_ent) as f32;
+    world.entity_set_position_xy(cache.debug_ent, SCREEN_XRES as f32 - 4.0 - w, 4.0);
+}
+
+
+// running timer, and the latest splits against the best run
+fn update_speedrun(cache: &mut game::GameData, heap: &mut game::GameDataHeap, world: &mut mgfw::ecs::World) {
+
+    let data = &heap.speedrun;
+
+    world.entity_set_visibility(cache.timer_ent, data.enabled);
+    if !data.enabled { return; }
+
+    let mut text = speedrun::format_time(data.ticks);
+    if !data.running && data.run_pb {
+        text = format!("{text} new best run!");
+    }
+    world.entity_set_text(cache.timer_ent, text);
+
+    let first = data.splits.len().saturating_sub(SPLIT_ROWS);
+    for i in 0..SPLIT_ROWS {
+        let ent = cache.split_ents[i];
+        let split = match data.splits.get(first + i) {
+            Some(split) => split,
+            None => {
+                world.entity_set_visibility(ent, false);
+                continue;
+            },
+        };
+
+        let mut text = format!("{}: {}", split.level, speedrun::format_time(split.ticks));
+        world.entity_set_color_rgba(ent, 0.7, 0.7, 0.7, 1.0);
+        if let Some(delta) = speedrun::delta(data, split) {
+            text = format!("{text} {}", speedrun::format_delta(delta));
+            match 0 > delta {
+                true => world.entity_set_color_rgba(ent, 0.4, 1.0, 0.4, 1.0),
+                false => world.entity_set_color_rgba(ent, 1.0, 0.4, 0.4, 1.0),
+            }
+        }
+        if split.best {
+            world.entity_set_color_rgba(ent, 1.0, 0.85, 0.3, 1.0);
+        }
+        world.entity_set_text(ent, text);
+        world.entity_set_visibility(ent, true);
+    }
+}
+
+
 fn check_win(cache: &mut game::GameData, heap: &mut game::GameDataHeap, world: &mut mgfw::ecs::World) {
 
     if cache.level_complete { return; }
 
-    // count active orbs vs num orbs
+    // count active orbs vs num orbs, beams only light orbs of their own color
     let mut nactive = 0;
     for i in 0..heap.scene_data.sz2 {
         if ORB_ACTIVE == heap.scene_data.beams[i] {
@@ -800,11 +1419,26 @@ fn check_win(cache: &mut game::GameData, heap: &mut game::GameDataHeap, world: &
         cache.ui_data.beam_hold = false;
         cache.ui_data.beam_origin = BOARD_IDX_INVALID;
         cache.ui_data.beam_alpha = 0.0;
+        cache.ui_data.hint_piece = HOLDING_INVALID;
+        cache.ui_data.hint_idx = BOARD_IDX_INVALID;
+        world.entity_set_visibility(cache.hint_ent, false);
+        game_state::cancel_hint(heap);
+        speedrun::split(&mut heap.speedrun, cache.player_data.level_displayed, cache.final_level);
+        game_state::complete_level(cache, heap);
+
+        // moves against par, complete_level settled the par
+        let moves = cache.player_data.moves;
+        let par = cache.player_data.par;
+        let n = game_state::stars(moves, par);
+        world.entity_set_text(cache.result_ent, format!("{moves} moves, par {par}   {}", "*".repeat(n)));
+        let w = world.text_get_width(cache.result_ent) as f32;
+        world.entity_set_position_xy(cache.result_ent, SCREEN_XRES_HALF as f32 - (w * 0.5).floor(), SCREEN_YRES_HALF as f32 + 24.0);
     }
 
 }
 
 
+
 pub fn transition(cache: &mut game::GameData, heap: &mut game::GameDataHeap, world: &mut mgfw::ecs::World) {
     
     cache.ui_data.transition_mode = TRANSITION_MODE_FLASH;
@@ -827,7 +1461,11 @@ fn update_transition(cache: &mut game::GameData, heap: &mut game::GameDataHeap,
             cache.ui_data.transition_mode = TRANSITION_MODE_INVALID;
             if MENU_MAIN == cache.ui_data.menu {
                 cache.ui_data.menu = MENU_GAME;
-                game_state::next_level(cache, heap, world);
+                if 0 != cache.player_data.daily {
+                    game_state::daily_level(cache, heap, world);
+                } else {
+                    game_state::next_level(cache, heap, world);
+                }
             }
         }
     }
@@ -855,14 +1493,20 @@ fn update_transition(cache: &mut game::GameData, heap: &mut game::GameDataHeap,
     if MENU_MAIN == cache.ui_data.menu && alpha < 1.0 {
         world.entity_set_visibility(cache.logo_ent, false);
         world.entity_set_visibility(cache.start_ent, false);
+        world.entity_set_visibility(cache.speedrun_ent, false);
+        world.entity_set_visibility(cache.daily_ent, false);
+        world.entity_set_visibility(cache.continue_ent, false);
+        world.entity_set_visibility(cache.select_ent, false);
     }
 
     world.entity_set_color_rgba(cache.transition_ent, 1.0, 1.0, 1.0, alpha);
     world.entity_set_color_rgba(cache.complete_ent, 1.0, 1.0, 1.0, alpha);
+    world.entity_set_color_rgba(cache.result_ent, 1.0, 1.0, 1.0, alpha);
 
     world.entity_set_tilemap(cache.transition_ent, cache.gradient_ent, 11, &data);
     world.entity_set_visibility(cache.transition_ent, TRANSITION_MODE_INVALID != cache.ui_data.transition_mode);
     world.entity_set_visibility(cache.complete_ent, TRANSITION_MODE_INVALID != cache.ui_data.transition_mode && MENU_GAME == cache.ui_data.menu);
+    world.entity_set_visibility(cache.result_ent, TRANSITION_MODE_INVALID != cache.ui_data.transition_mode && MENU_GAME == cache.ui_data.menu);
 
     world.entity_set_alpha(cache.blackout_ent, cache.blackout_alpha as f32 / 30.0);
     // hack
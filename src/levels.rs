@@ -0,0 +1,543 @@
+use super::enums::*;
+
+use std::fs::File;
+use std::io::{self, BufRead};
+
+#[derive(Clone)]
+pub struct Block {
+    pub sz: usize,
+    pub norbs: usize,
+    pub npcs: usize,
+    pub pieces: Vec<u16>, // inventory in the order it is laid out, npcs long
+    pub listed: bool, // pieces came from the line's piece list, not the standard cycle
+    pub par: usize, // moves to aim for, 0 leaves it to the solver
+    pub map: Vec<u8>,
+}
+
+impl Block {
+    pub fn empty() -> Block {
+        Block {
+            sz: 0,
+            norbs: 0,
+            npcs: 0,
+            pieces: Vec::new(),
+            listed: false,
+            par: 0,
+            map: Vec::new(),
+        }
+    }
+}
+
+
+// levels without a piece list cycle through every reflector in this order
+const STANDARD_CYCLE: [u16; 8] = [
+    REFLECTOR_TL, REFLECTOR_TR, REFLECTOR_U, REFLECTOR_L,
+    REFLECTOR_BL, REFLECTOR_BR, REFLECTOR_R, REFLECTOR_D,
+];
+
+pub fn standard_pieces(npcs: usize) -> Vec<u16> {
+    (0..npcs).map(|i| STANDARD_CYCLE[i % STANDARD_CYCLE.len()]).collect()
+}
+
+// a piece count that stops part way through the cycle, such a level lists its pieces
+pub fn needs_piece_list(npcs: usize) -> bool {
+    !npcs.is_multiple_of(STANDARD_CYCLE.len())
+}
+
+
+// every map line in a levels.dat file with its line number, parsed or why not
+pub fn read_blocks(filename: &str) -> io::Result<Vec<(usize, Result<Block, String>)>> {
+
+    let file = File::open(filename)?;
+
+    let mut blocks = Vec::new();
+
+    let reader = io::BufReader::new(file);
+    for (n, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.len() < 2 { continue; }
+        blocks.push((n + 1, parse_block(&line)));
+    }
+
+    Ok(blocks)
+}
+
+
+// read every block in a levels.dat file, reporting and skipping bad lines
+pub fn load_blocks(filename: &str) -> io::Result<Vec<Block>> {
+
+    let mut blocks: Vec<Block> = Vec::new();
+
+    for (lineno, block) in read_blocks(filename)? {
+        match block {
+            Ok(block) => blocks.push(block),
+            Err(err) => println!("{filename}:{lineno}: {err}, skipping"),
+        }
+    }
+
+    Ok(blocks)
+}
+
+
+// one levels.dat line, npcs,sz,norbs,map with the map compressed, then
+// optionally the pieces, one locked piece cell per piece, and the par.
+// an empty piece list keeps the standard one
+pub fn parse_block(line: &str) -> Result<Block, String> {
+
+    let split: Vec<&str> = line.split(',').collect();
+    if 4 > split.len() || 6 < split.len() {
+        return Err(format!("expected 4 to 6 fields, found {}", split.len()));
+    }
+
+    let field = |i: usize, name: &str| match split[i].trim().parse::<usize>() {
+        Ok(v) => Ok(v),
+        Err(_) => Err(format!("bad {name} '{}'", split[i])),
+    };
+
+    let mut block = Block::empty();
+    block.npcs = field(0, "npcs")?;
+    block.sz = field(1, "sz")?;
+    block.norbs = field(2, "norbs")?;
+
+    if 3 > block.sz || MAP_MAX_WIDTH < block.sz {
+        return Err(format!("sz {} outside 3..={MAP_MAX_WIDTH}", block.sz));
+    }
+
+    if 0 == block.npcs || MAX_PIECES < block.npcs {
+        return Err(format!("npcs {} outside 1..={MAX_PIECES}", block.npcs));
+    }
+
+    block.pieces = standard_pieces(block.npcs);
+    if 5 <= split.len() && !split[4].trim().is_empty() {
+        block.pieces = Vec::new();
+        block.listed = true;
+        for c in split[4].trim().bytes() {
+            match cell_piece(c) {
+                REFLECTOR_INVALID => return Err(format!("bad piece '{}'", char::from(c))),
+                tile => block.pieces.push(tile),
+            }
+        }
+        if block.npcs != block.pieces.len() {
+            return Err(format!("npcs is {} but {} pieces are listed", block.npcs, block.pieces.len()));
+        }
+    }
+
+    if 6 == split.len() {
+        block.par = field(5, "par")?;
+        if 0 == block.par {
+            return Err(String::from("par has to be at least 1"));
+        }
+    }
+
+    block.map = decode_cells(split[3])?;
+    if block.sz * block.sz != block.map.len() {
+        return Err(format!("map expands to {} cells, expected {}", block.map.len(), block.sz * block.sz));
+    }
+
+    // portals only work in pairs
+    for k in 0..NUM_PORTAL_PAIRS {
+        let cell = b'1' + k as u8;
+        let count = block.map.iter().filter(|c| cell == **c).count();
+        if 0 != count && 2 != count {
+            return Err(format!("portal {} appears {count} times, expected 2", char::from(cell)));
+        }
+    }
+
+    Ok(block)
+}
+
+
+// a block as one levels.dat line, the format load_blocks reads.
+// the piece list and par are only written out when they are needed
+pub fn encode_block(block: &Block) -> String {
+    let line = format!("{},{},{},{}", block.npcs, block.sz, block.norbs, escape(&block.map));
+
+    let mut pieces = String::new();
+    if block.listed || standard_pieces(block.npcs) != block.pieces {
+        pieces = block.pieces.iter().map(|t| char::from(piece_cell(*t))).collect();
+    }
+
+    match (pieces.is_empty(), block.par) {
+        (true, 0) => line,
+        (false, 0) => format!("{line},{pieces}"),
+        (_, par) => format!("{line},{pieces},{par}"),
+    }
+}
+
+
+// color of an orb, filter or source cell in the expanded map
+pub fn cell_color(cell: u8) -> u8 {
+    match cell {
+        b'r' | b'R' => COLOR_RED,
+        b'g' | b'G' => COLOR_GREEN,
+        b'b' | b'B' => COLOR_BLUE,
+        _ => COLOR_WHITE,
+    }
+}
+
+// plain orbs are 'o', colored ones r, g and b
+pub fn is_orb_cell(cell: u8) -> bool {
+    CELL_ORB == cell || b'r' == cell || b'g' == cell || b'b' == cell
+}
+
+// R, G and B are filters inside the board and color the source on an edge
+pub fn is_filter_cell(cell: u8) -> bool {
+    b'R' == cell || b'G' == cell || b'B' == cell
+}
+
+// portals are 1 to 4, the two cells with the same digit are linked
+pub fn is_portal_cell(cell: u8) -> bool {
+    (b'1'..b'1' + NUM_PORTAL_PAIRS as u8).contains(&cell)
+}
+
+// locked reflectors, one cell per REFLECTOR_TL..REFLECTOR_R. piece lists use them too
+const PIECE_CELLS: &[u8; 8] = b"[]{}v<^>";
+
+// reflector the level locks on this cell, REFLECTOR_INVALID for none
+pub fn cell_piece(cell: u8) -> u16 {
+    match PIECE_CELLS.iter().position(|c| cell == *c) {
+        Some(k) => REFLECTOR_TL + k as u16,
+        None => REFLECTOR_INVALID,
+    }
+}
+
+
+// cell for a reflector, the inverse of cell_piece
+pub fn piece_cell(tile: u16) -> u8 {
+    PIECE_CELLS[(tile - REFLECTOR_TL) as usize]
+}
+
+
+// any cell map in the compressed levels.dat alphabet, puzzle codes use it too
+pub fn encode_cells(map: &[u8]) -> String {
+    escape(map)
+}
+
+pub fn decode_cells(txt: &str) -> Result<Vec<u8>, String> {
+    if let Some(c) = txt.chars().find(|c| !is_map_char(*c)) {
+        return Err(format!("bad map character '{c}'"));
+    }
+    unescape(txt)
+}
+
+
+fn is_map_char(c: char) -> bool {
+    c.is_ascii_alphabetic() || '.' == c || ESCAPE == c || is_portal_cell(c as u8) || DIGRAPHS.iter().any(|d| d.0.starts_with(c))
+}
+
+
+// (cell, reflector) for every piece the level locks in place
+pub fn locked_pieces(block: &Block) -> Vec<(usize, u16)> {
+    let mut ret = Vec::new();
+    for i in 0..block.map.len() {
+        let tile = cell_piece(block.map[i]);
+        if REFLECTOR_INVALID != tile {
+            ret.push((i, tile));
+        }
+    }
+    ret
+}
+
+
+// what mirrorb-lint has to say about one parsed block on its own
+pub fn lint_block(block: &Block) -> Vec<String> {
+
+    let mut problems: Vec<String> = Vec::new();
+
+    let orbs = block.map.iter().filter(|c| is_orb_cell(**c)).count();
+    if orbs != block.norbs {
+        problems.push(format!("norbs is {} but the map has {orbs} orbs", block.norbs));
+    }
+
+    // without a piece list the inventory repeats all 8 reflectors
+    if needs_piece_list(block.npcs) && !block.listed {
+        problems.push(format!("npcs is {} with no piece list, expected a multiple of 8", block.npcs));
+    }
+
+    let known = |c: u8| CELL_FLOOR == c || CELL_PATH == c || is_orb_cell(c) || is_filter_cell(c) || is_portal_cell(c) ||
+        REFLECTOR_INVALID != cell_piece(c);
+    if let Some(c) = block.map.iter().find(|c| !known(**c)) {
+        problems.push(format!("unexpected map cell '{}'", char::from(*c)));
+    }
+
+    // locked pieces come out of the inventory
+    let mut locked = [0; 8];
+    let mut have = [0; 8];
+    for (_, tile) in locked_pieces(block) {
+        locked[(tile - REFLECTOR_TL) as usize] += 1;
+    }
+    for tile in block.pieces.iter() {
+        have[(tile - REFLECTOR_TL) as usize] += 1;
+    }
+    if let Some(k) = (0..8).find(|k| have[*k] < locked[*k]) {
+        problems.push(format!("{} locked pieces of type {k}, the inventory only has {}", locked[k], have[k]));
+    }
+
+    // orbs, portals, locked pieces and path cells have to sit inside the ring of sources,
+    // only source colors go on the edge
+    let sz = block.sz;
+    for i in 0..block.map.len() {
+        let x = i % sz;
+        let y = i / sz;
+        if (0 == x || 0 == y || sz - 1 == x || sz - 1 == y) && CELL_FLOOR != block.map[i] && !is_filter_cell(block.map[i]) {
+            problems.push(format!("map cell {x},{y} on the edge is '{}'", char::from(block.map[i])));
+            break;
+        }
+    }
+
+    problems
+}
+
+
+// colored cells, portals and locked pieces don't fit the run-length alphabet,
+// so the compressed map spells each one out as a quote followed by the cell itself
+const ESCAPE: char = '\'';
+
+fn is_escaped_cell(cell: u8) -> bool {
+    COLOR_WHITE != cell_color(cell) || is_portal_cell(cell) || REFLECTOR_INVALID != cell_piece(cell)
+}
+
+fn unescape(txt: &str) -> Result<Vec<u8>, String> {
+
+    let mut map = Vec::new();
+
+    for (k, part) in txt.split(ESCAPE).enumerate() {
+        let mut part = part;
+        if 0 < k {
+            let cell = part.bytes().next().unwrap_or(0);
+            if !is_escaped_cell(cell) {
+                return Err(format!("bad escaped cell '{}'", char::from(cell)));
+            }
+            map.push(cell);
+            part = &part[1..];
+        }
+        let cells = expand(decompress(part.to_string()));
+        if let Some(c) = cells.iter().find(|c| CELL_FLOOR != **c && CELL_ORB != **c && CELL_PATH != **c) {
+            return Err(format!("cell '{}' has to be escaped", char::from(*c)));
+        }
+        map.extend(cells);
+    }
+
+    Ok(map)
+}
+
+fn escape(map: &[u8]) -> String {
+
+    let mut out = String::new();
+
+    let mut start = 0;
+    for i in 0..map.len() {
+        if is_escaped_cell(map[i]) {
+            out += &compress(contract(&map[start..i]));
+            out.push(ESCAPE);
+            out.push(char::from(map[i]));
+            start = i + 1;
+        }
+    }
+    out += &compress(contract(&map[start..]));
+
+    out
+}
+
+
+// symbol substitutions for common pairs in the run-length map, applied in order
+#[rustfmt::skip]
+const DIGRAPHS: [(&str, &str); 37] = [
+    ("\"", "lA"),
+    ("/", ".&"),
+    ("|", ".i"),
+    ("`", "cB"),
+    ("?", "~~"),
+    (">", ".z"),
+    ("<", ".h"),
+    (";", "#A"),
+    (":", ".F"),
+    ("}", "kA"),
+    ("{", "jA"),
+    ("]", "bB"),
+    ("[", ".g"),
+    ("=", "$A"),
+    ("_", "aB"),
+    ("0", ".!"),
+    ("9", ".f"),
+    ("8", "hA"),
+    ("7", "iA"),
+    ("6", ".E"),
+    ("5", ".D"),
+    ("4", ".~"),
+    ("2", "gA"),
+    ("1", ".e"),
+    ("+", ".a"),
+    ("-", "fA"),
+    (")", "dA"),
+    ("(", ".C"),
+    ("*", "cA"),
+    ("&", "eA"),
+    ("^", ".B"),
+    ("%", ".b"),
+    ("$", ".d"),
+    ("#", ".c"),
+    ("@", ".A"),
+    ("!", "bA"),
+    ("~", "aA"),
+];
+
+fn decompress(txt: String) -> String {
+    let mut out = txt;
+
+    for (symbol, pair) in DIGRAPHS {
+        out = out.replace(symbol, pair);
+    }
+
+    out
+}
+
+// inverse of decompress, undoing the substitutions from last to first
+fn compress(txt: String) -> String {
+    let mut out = txt;
+
+    for (symbol, pair) in DIGRAPHS.iter().rev() {
+        out = out.replace(pair, symbol);
+    }
+
+    out
+}
+
+fn expand(txt: String) -> Vec<u8> {
+
+    let mut out = txt;
+
+    out = out.replace(".", "_");
+
+    let mut rhs = String::new();
+    for i in 1..27 {
+        let lhs = char::from(96 + i);
+        rhs = format!("{rhs}.");
+        out = out.replace(lhs, rhs.as_str());
+    }
+
+    let mut rhs = String::new();
+    for i in 1..27 {
+        let lhs = char::from(64 + i);
+        rhs = format!("{rhs}x");
+        out = out.replace(lhs, rhs.as_str());
+    }
+
+    out = out.replace("_", "o");
+
+    out.as_bytes().to_vec()
+}
+
+// inverse of expand, runs of '.' become a..z and runs of 'x' become A..Z
+fn contract(map: &[u8]) -> String {
+
+    let mut out = String::new();
+
+    let mut i = 0;
+    while i < map.len() {
+        let c = map[i];
+
+        // orbs stand alone
+        if CELL_ORB == c {
+            out.push('.');
+            i += 1;
+            continue;
+        }
+
+        let mut run = 1;
+        while i + run < map.len() && c == map[i + run] && 26 > run {
+            run += 1;
+        }
+
+        let base = if CELL_PATH == c { 64 } else { 96 };
+        out.push(char::from(base + run as u8));
+        i += run;
+    }
+
+    out
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_same(a: &Block, b: &Block) {
+        assert_eq!(a.sz, b.sz);
+        assert_eq!(a.norbs, b.norbs);
+        assert_eq!(a.npcs, b.npcs);
+        assert_eq!(a.pieces, b.pieces);
+        assert_eq!(a.listed, b.listed);
+        assert_eq!(a.par, b.par);
+        assert_eq!(a.map, b.map);
+    }
+
+    #[test]
+    fn shipped_levels_round_trip() {
+        let blocks = load_blocks("assets/levels.dat").unwrap();
+        assert!(!blocks.is_empty());
+
+        for block in &blocks {
+            let line = encode_block(block);
+            let back = parse_block(&line).unwrap();
+            assert_same(block, &back);
+            assert_eq!(line, encode_block(&back));
+        }
+    }
+
+    #[test]
+    fn piece_list_par_and_new_cells_round_trip() {
+        let mut map = vec![CELL_FLOOR; 7 * 7];
+        map[8] = b'R';
+        map[10] = CELL_ORB;
+        map[12] = b'g';
+        map[16] = b'1';
+        map[32] = b'1';
+        map[24] = CELL_PATH;
+        map[26] = piece_cell(REFLECTOR_U);
+
+        let block = Block {
+            sz: 7,
+            norbs: 2,
+            npcs: 3,
+            pieces: vec![REFLECTOR_U, REFLECTOR_TL, REFLECTOR_R],
+            listed: true,
+            par: 5,
+            map,
+        };
+        let back = parse_block(&encode_block(&block)).unwrap();
+        assert_same(&block, &back);
+    }
+
+    #[test]
+    fn written_out_standard_list_is_kept_apart() {
+        // the same inventory, once left to the cycle and once listed
+        let bare = parse_block("3,5,0,y").unwrap();
+        assert!(!bare.listed);
+        assert_eq!(standard_pieces(3), bare.pieces);
+
+        let cells: String = standard_pieces(3).iter().map(|t| char::from(piece_cell(*t))).collect();
+        let listed = parse_block(&format!("3,5,0,y,{cells}")).unwrap();
+        assert!(listed.listed);
+        assert_eq!(bare.pieces, listed.pieces);
+        assert_eq!(format!("3,5,0,y,{cells}"), encode_block(&listed));
+    }
+
+    #[test]
+    fn too_many_pieces_is_rejected() {
+        let mut block = Block {
+            sz: 7,
+            norbs: 0,
+            npcs: MAX_PIECES,
+            pieces: standard_pieces(MAX_PIECES),
+            listed: false,
+            par: 0,
+            map: vec![CELL_FLOOR; 7 * 7],
+        };
+        assert!(parse_block(&encode_block(&block)).is_ok());
+
+        block.npcs = MAX_PIECES + 1;
+        block.pieces = standard_pieces(MAX_PIECES + 1);
+        assert!(parse_block(&encode_block(&block)).is_err());
+    }
+}
@@ -0,0 +1,3 @@
+// the level format, shared by the game and the level tools
+pub mod enums;
+pub mod levels;
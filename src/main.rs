@@ -1,15 +1,67 @@
 mod game;
 mod mgfw;
 
+use std::process::ExitCode;
+
 const TITLE: &str = "mirr/orb";
 const XRES: i32 = game::enums::SCREEN_XRES as i32;
 const YRES: i32 = game::enums::SCREEN_YRES as i32;
 
 
-fn main() {
+fn main() -> ExitCode {
+    // headless tools
+    let args: Vec<String> = std::env::args().collect();
+    // --solve [map index|all] [max nodes]
+    if 1 < args.len() && "--solve" == args[1] {
+        let only = match args.get(2).map(|arg| arg.as_str()) {
+            None | Some("all") => Ok(None),
+            Some(arg) => arg.parse::<usize>().map(Some),
+        };
+        let max_nodes = match args.get(3) {
+            Some(arg) => arg.parse::<usize>(),
+            None => Ok(1_000_000),
+        };
+        return match (only, max_nodes) {
+            (Ok(only), Ok(max_nodes)) => {
+                game::solver::report("assets/levels.dat", only, max_nodes);
+                ExitCode::SUCCESS
+            },
+            _ => {
+                eprintln!("usage: mirrorb --solve [map index|all] [max nodes]");
+                ExitCode::FAILURE
+            },
+        };
+    }
+
+    // --generate [sz] [npcs] [norbs] [count]
+    if 1 < args.len() && "--generate" == args[1] {
+        let arg = |i: usize, default: usize| match args.get(i) {
+            Some(arg) => arg.parse::<usize>(),
+            None => Ok(default),
+        };
+        return match (arg(2, 7), arg(3, 8), arg(4, 2), arg(5, 1)) {
+            (Ok(sz), Ok(npcs), Ok(norbs), Ok(count)) => {
+                if game::generator::report(sz, npcs, norbs, count) { ExitCode::SUCCESS } else { ExitCode::FAILURE }
+            },
+            _ => {
+                eprintln!("usage: mirrorb --generate [sz] [npcs] [norbs] [count]");
+                ExitCode::FAILURE
+            },
+        };
+    }
+
     let el = glutin::event_loop::EventLoop::new();
     let mut core = mgfw::Core::new(TITLE, XRES, YRES, &el);
 
+    // --record <file> saves the session's input, --replay <file> plays one back
+    for i in 1..args.len() {
+        match (args[i].as_str(), args.get(i + 1)) {
+            ("--record", Some(filename)) => core.record(filename),
+            ("--replay", Some(filename)) => core.replay(filename),
+            _ => (),
+        }
+    }
+
     el.run(move |event, _, control_flow| {
         core.check_events(&event);
         if core.ready_to_quit() {
@@ -1,6 +1,8 @@
+use crate::mgfw::input::InputMap;
 use crate::mgfw::log;
 use rand;
 use rand::prelude::*;
+use rand_chacha::ChaCha8Rng;
 use std::fs::File;
 use std::io::{self, BufRead};
 use std::ops::Range;
@@ -27,15 +29,21 @@ pub struct World {
     pub mouse_y: i32,
     pub gamepad_x: f32,
     pub gamepad_y: f32,
-    rng: ThreadRng,
+    seed: u64,
+    rng: ChaCha8Rng,
     pub mgui: std::boxed::Box<Mgui>,
     pub ugui: std::boxed::Box<Ugui>,
+    pub input: std::boxed::Box<InputMap>,
+    recorded: bool,
+    pins: Vec<(String, String)>, // replayed values still to hand back, see pin
+    new_pins: Vec<(String, String)>, // values taken while recording
 }
 
 #[allow(dead_code)]
 impl World {
     pub fn new(cache: &mut CacheManager) -> World {
         log(format!("Constructing World"));
+        let seed = rand::random();
         World {
             ent: Box::new(EntityRegistry::new(cache)),
             pcm: Box::new(PositionComponentManager::new(cache)),
@@ -55,9 +63,14 @@ impl World {
             mouse_y: 0,
             gamepad_x: 0.0,
             gamepad_y: 0.0,
-            rng: rand::thread_rng(),
+            seed,
+            rng: ChaCha8Rng::seed_from_u64(seed),
             mgui: Box::new(Mgui::new()),
             ugui: Box::new(Ugui::new()),
+            input: Box::new(InputMap::new()),
+            recorded: false,
+            pins: Vec::new(),
+            new_pins: Vec::new(),
         }
     }
 
@@ -78,6 +91,51 @@ impl World {
         self.fcm.clear();
     }
 
+    // restart the generator, the same seed gives the same sequence
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = seed;
+        self.rng = ChaCha8Rng::seed_from_u64(seed);
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn rng(&mut self) -> &mut ChaCha8Rng {
+        &mut self.rng
+    }
+
+    // a --record or --replay session, with the values the recording pinned
+    pub fn start_recorded_session(&mut self, pins: Vec<(String, String)>) {
+        self.recorded = true;
+        self.pins = pins;
+    }
+
+    // a recorded session has to play the same wherever it is replayed,
+    // so it leaves the local save files alone
+    pub fn is_recorded_session(&self) -> bool {
+        self.recorded
+    }
+
+    // a value from outside the session, like the date or a file the player
+    // loads. a replay hands back what the recording saw under the same key,
+    // in the order it was taken, anything else gets value
+    pub fn pin(&mut self, key: &str, value: impl FnOnce() -> String) -> String {
+        if let Some(i) = self.pins.iter().position(|(k, _)| key == k) {
+            return self.pins.remove(i).1;
+        }
+        let value = value();
+        if self.recorded {
+            self.new_pins.push((String::from(key), value.clone()));
+        }
+        value
+    }
+
+    // values pinned since the last call, for the recording
+    pub fn take_pins(&mut self) -> Vec<(String, String)> {
+        std::mem::take(&mut self.new_pins)
+    }
+
     pub fn rnd(&mut self) -> f32 {
         self.rng.gen()
     }
@@ -0,0 +1,284 @@
+use std::fs::File;
+use std::io::{self, BufRead, Write};
+
+use super::*;
+
+// a raw input bound to an action, by the event it sends going down and the one
+// coming back up. an input that only reports one of them is a tap, pressed and
+// released at once
+struct Binding {
+    action: u8,
+    press: u8,
+    release: u8,
+    down: bool,
+}
+
+// named actions on top of the raw input events. a game defines its actions with
+// default inputs and a bindings file can move them. the state follows the events
+// as the core hands them to the game, so a replay plays the same actions.
+// pressed, repeated and released are for the event just taken, held lasts until
+// every input bound to the action is let go
+pub struct InputMap {
+    names: Vec<String>, // by action id
+    inputs: Vec<Vec<String>>, // input names bound to each action
+    bindings: Vec<Binding>,
+    pressed: Vec<bool>,
+    repeated: Vec<bool>, // pressed, or key repeat while held
+    released: Vec<bool>,
+    replayed_held: Option<Vec<bool>>, // held as a replay recorded it, see set_state
+}
+
+#[allow(dead_code)]
+impl InputMap {
+    pub fn new() -> InputMap {
+        InputMap {
+            names: Vec::new(),
+            inputs: Vec::new(),
+            bindings: Vec::new(),
+            pressed: Vec::new(),
+            repeated: Vec::new(),
+            released: Vec::new(),
+            replayed_held: None,
+        }
+    }
+
+    // action ids are small and dense, they index the state
+    pub fn define(&mut self, action: u8, name: &str, inputs: &[&str]) {
+        let a = action as usize;
+        if self.names.len() <= a {
+            self.names.resize(a + 1, String::new());
+            self.inputs.resize(a + 1, Vec::new());
+            self.pressed.resize(a + 1, false);
+            self.repeated.resize(a + 1, false);
+            self.released.resize(a + 1, false);
+        }
+        self.names[a] = String::from(name);
+        let inputs: Vec<String> = inputs.iter().map(|i| String::from(*i)).collect();
+        self.bind(action, &inputs);
+    }
+
+    // replace the inputs bound to an action, unknown input names are skipped
+    pub fn bind(&mut self, action: u8, inputs: &[String]) {
+        self.bindings.retain(|b| action != b.action);
+        self.inputs[action as usize].clear();
+        for name in inputs {
+            match input_events(name) {
+                Some((press, release)) => {
+                    self.bindings.push(Binding { action, press, release, down: false });
+                    self.inputs[action as usize].push(name.clone());
+                },
+                None => log(format!("unknown input '{name}' for {}", self.names[action as usize])),
+            }
+        }
+    }
+
+    pub fn action(&self, name: &str) -> Option<u8> {
+        self.names.iter().position(|n| name == n).map(|a| a as u8)
+    }
+
+    // <action>,<input>,<input>.. per line, an action left out keeps its defaults
+    // and one with no inputs is unbound. inputs are key names like z, f5, space,
+    // pgup or return, mouse, mouse_left, mouse_right, and pad_a or pad_up
+    pub fn load(&mut self, filename: &str) -> io::Result<()> {
+        let reader = io::BufReader::new(File::open(filename)?);
+        for line in reader.lines() {
+            let line = line?;
+            let split: Vec<String> = line.trim().split(',').map(|v| String::from(v.trim())).collect();
+            if split[0].is_empty() { continue; }
+            match self.action(&split[0]) {
+                Some(action) => self.bind(action, &split[1..].iter().filter(|v| !v.is_empty()).cloned().collect::<Vec<String>>()),
+                None => log(format!("{filename}: unknown action '{}'", split[0])),
+            }
+        }
+        Ok(())
+    }
+
+    pub fn save(&self, filename: &str) -> io::Result<()> {
+        let mut file = File::create(filename)?;
+        for a in 0..self.names.len() {
+            if self.names[a].is_empty() { continue; }
+            let mut line = self.names[a].clone();
+            for i in &self.inputs[a] {
+                line = format!("{line},{i}");
+            }
+            writeln!(file, "{line}")?;
+        }
+        Ok(())
+    }
+
+    pub fn event(&mut self, event: u8) {
+        self.replayed_held = None;
+        let before: Vec<bool> = (0..self.names.len()).map(|a| self.held(a as u8)).collect();
+
+        self.pressed.fill(false);
+        self.repeated.fill(false);
+        self.released.fill(false);
+        if EVENT_INVALID == event { return; }
+
+        for b in self.bindings.iter_mut() {
+            let a = b.action as usize;
+            let tap = if event == b.press {
+                self.repeated[a] = true;
+                b.down = EVENT_INVALID != b.release;
+                !b.down
+            } else if event == b.release {
+                let tap = !b.down;
+                b.down = false;
+                tap
+            } else {
+                false
+            };
+            if tap {
+                self.pressed[a] = true;
+                self.repeated[a] = true;
+                self.released[a] = true;
+            }
+        }
+
+        for (a, held) in before.into_iter().enumerate() {
+            if held == self.held(a as u8) { continue; }
+            match held {
+                false => self.pressed[a] = true,
+                true => self.released[a] = true,
+            }
+        }
+    }
+
+    pub fn pressed(&self, action: u8) -> bool {
+        self.pressed.get(action as usize).copied().unwrap_or(false)
+    }
+
+    pub fn repeated(&self, action: u8) -> bool {
+        self.repeated.get(action as usize).copied().unwrap_or(false)
+    }
+
+    pub fn released(&self, action: u8) -> bool {
+        self.released.get(action as usize).copied().unwrap_or(false)
+    }
+
+    pub fn held(&self, action: u8) -> bool {
+        match &self.replayed_held {
+            Some(held) => held.get(action as usize).copied().unwrap_or(false),
+            None => self.bindings.iter().any(|b| action == b.action && b.down),
+        }
+    }
+
+    // pressed, repeated, released and held after the event just taken, one bit
+    // per action id. a replay records this rather than the raw event so it
+    // plays back the same under someone else's bindings
+    pub fn state(&self) -> [u64; 4] {
+        let mut ret = [0; 4];
+        for a in 0..self.names.len().min(64) {
+            let flags = [self.pressed[a], self.repeated[a], self.released[a], self.held(a as u8)];
+            for k in 0..4 {
+                if flags[k] { ret[k] |= 1 << a; }
+            }
+        }
+        ret
+    }
+
+    // take the state a replay recorded in place of the event it came from
+    pub fn set_state(&mut self, state: [u64; 4]) {
+        let n = self.names.len().min(64);
+        let bit = |k: usize, a: usize| 0 != state[k] & (1 << a);
+        for a in 0..n {
+            self.pressed[a] = bit(0, a);
+            self.repeated[a] = bit(1, a);
+            self.released[a] = bit(2, a);
+        }
+        self.replayed_held = Some((0..n).map(|a| bit(3, a)).collect());
+    }
+}
+
+impl Default for InputMap {
+    fn default() -> Self {
+        InputMap::new()
+    }
+}
+
+
+// the going down and coming back up events of a named input, EVENT_INVALID
+// where the input doesn't send one
+#[rustfmt::skip]
+fn input_events(name: &str) -> Option<(u8, u8)> {
+    let ret = match name {
+        "mouse" => (EVENT_INPUT_MOUSE_BUTTON_DOWN, EVENT_INPUT_MOUSE_BUTTON_UP),
+        "mouse_left" => (EVENT_INPUT_MOUSE_BUTTON_LEFT_DOWN, EVENT_INPUT_MOUSE_BUTTON_LEFT_UP),
+        "mouse_right" => (EVENT_INPUT_MOUSE_BUTTON_RIGHT_DOWN, EVENT_INPUT_MOUSE_BUTTON_RIGHT_UP),
+
+        "escape" => (EVENT_INPUT_KEYBOARD_PRESSED_ESCAPE, EVENT_INPUT_KEYBOARD_RELEASED_ESCAPE),
+        "return" => (EVENT_INPUT_KEYBOARD_PRESSED_RETURN, EVENT_INPUT_KEYBOARD_RELEASED_RETURN),
+        "up" => (EVENT_INPUT_KEYBOARD_PRESSED_UP, EVENT_INPUT_KEYBOARD_RELEASED_UP),
+        "down" => (EVENT_INPUT_KEYBOARD_PRESSED_DOWN, EVENT_INPUT_KEYBOARD_RELEASED_DOWN),
+        "left" => (EVENT_INPUT_KEYBOARD_PRESSED_LEFT, EVENT_INPUT_KEYBOARD_RELEASED_LEFT),
+        "right" => (EVENT_INPUT_KEYBOARD_PRESSED_RIGHT, EVENT_INPUT_KEYBOARD_RELEASED_RIGHT),
+        "space" => (EVENT_INPUT_KEYBOARD_PRESSED_SPACE, EVENT_INPUT_KEYBOARD_RELEASED_SPACE),
+        "tab" => (EVENT_INPUT_KEYBOARD_PRESSED_TAB, EVENT_INPUT_KEYBOARD_RELEASED_TAB),
+        "lctrl" => (EVENT_INPUT_KEYBOARD_PRESSED_LCTRL, EVENT_INPUT_KEYBOARD_RELEASED_LCTRL),
+        "lshift" => (EVENT_INPUT_KEYBOARD_PRESSED_LSHIFT, EVENT_INPUT_KEYBOARD_RELEASED_LSHIFT),
+        "lalt" => (EVENT_INPUT_KEYBOARD_PRESSED_LALT, EVENT_INPUT_KEYBOARD_RELEASED_LALT),
+        "plus" => (EVENT_INPUT_KEYBOARD_PRESSED_PLUS, EVENT_INPUT_KEYBOARD_RELEASED_PLUS),
+        "minus" => (EVENT_INPUT_KEYBOARD_PRESSED_MINUS, EVENT_INPUT_KEYBOARD_RELEASED_MINUS),
+        "backspace" => (EVENT_INVALID, EVENT_INPUT_KEYBOARD_RELEASED_BACKSPACE),
+        "pgup" => (EVENT_INVALID, EVENT_INPUT_KEYBOARD_RELEASED_PGUP),
+        "pgdn" => (EVENT_INVALID, EVENT_INPUT_KEYBOARD_RELEASED_PGDN),
+        "grave" => (EVENT_INVALID, EVENT_INPUT_KEYBOARD_RELEASED_GRAVE),
+        "insert" => (EVENT_INVALID, EVENT_INPUT_KEYBOARD_RELEASED_INSERT),
+        "delete" => (EVENT_INVALID, EVENT_INPUT_KEYBOARD_RELEASED_DELETE),
+
+        "pad_a" => (EVENT_INPUT_GAMEPAD_PRESSED_A, EVENT_INPUT_GAMEPAD_RELEASED_A),
+        "pad_b" => (EVENT_INPUT_GAMEPAD_PRESSED_B, EVENT_INPUT_GAMEPAD_RELEASED_B),
+        "pad_x" => (EVENT_INPUT_GAMEPAD_PRESSED_X, EVENT_INPUT_GAMEPAD_RELEASED_X),
+        "pad_y" => (EVENT_INPUT_GAMEPAD_PRESSED_Y, EVENT_INPUT_GAMEPAD_RELEASED_Y),
+        "pad_up" => (EVENT_INPUT_GAMEPAD_PRESSED_UP, EVENT_INVALID),
+        "pad_down" => (EVENT_INPUT_GAMEPAD_PRESSED_DOWN, EVENT_INVALID),
+        "pad_left" => (EVENT_INPUT_GAMEPAD_PRESSED_LEFT, EVENT_INVALID),
+        "pad_right" => (EVENT_INPUT_GAMEPAD_PRESSED_RIGHT, EVENT_INVALID),
+
+        // letters, digits and f1 to f12 only report letting go
+        _ => {
+            let b = name.as_bytes();
+            if 1 == b.len() && b[0].is_ascii_lowercase() {
+                (EVENT_INVALID, EVENT_INPUT_KEYBOARD_RELEASED_A + (b[0] - b'a'))
+            } else if 1 == b.len() && b[0].is_ascii_digit() {
+                (EVENT_INVALID, EVENT_INPUT_KEYBOARD_RELEASED_0 + (b[0] - b'0'))
+            } else {
+                match name.strip_prefix('f').and_then(|v| v.parse::<u8>().ok()) {
+                    Some(n) if (1..=12).contains(&n) => (EVENT_INVALID, EVENT_INPUT_KEYBOARD_RELEASED_F1 + (n - 1)),
+                    _ => return None,
+                }
+            }
+        },
+    };
+    Some(ret)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replayed_state_ignores_local_bindings() {
+        let mut recorded = InputMap::new();
+        recorded.define(0, "click", &["mouse_left"]);
+        recorded.define(1, "undo", &["z"]);
+
+        let mut local = InputMap::new();
+        local.define(0, "click", &["space"]);
+        local.define(1, "undo", &["u"]);
+
+        recorded.event(EVENT_INPUT_MOUSE_BUTTON_LEFT_DOWN);
+        local.set_state(recorded.state());
+        assert!(local.pressed(0) && local.held(0));
+
+        recorded.event(EVENT_INPUT_KEYBOARD_RELEASED_Z);
+        local.set_state(recorded.state());
+        assert!(local.pressed(1) && local.released(1));
+        assert!(!local.pressed(0) && local.held(0));
+
+        recorded.event(EVENT_INPUT_MOUSE_BUTTON_LEFT_UP);
+        local.set_state(recorded.state());
+        assert!(local.released(0) && !local.held(0));
+    }
+}
@@ -3,6 +3,8 @@
 pub mod cache;
 pub mod ecs;
 mod fonts;
+pub mod input;
+pub mod replay;
 mod support;
 
 use crate::game::GameWrapper;
@@ -68,6 +70,11 @@ pub const EVENT_INPUT_GAMEPAD_RELEASED_A: u8 = 15;
 pub const EVENT_INPUT_GAMEPAD_RELEASED_B: u8 = 16;
 pub const EVENT_INPUT_GAMEPAD_RELEASED_X: u8 = 17;
 pub const EVENT_INPUT_GAMEPAD_RELEASED_Y: u8 = 18;
+// d-pad presses, the left stick sends them too as it is pushed past half way
+pub const EVENT_INPUT_GAMEPAD_PRESSED_UP: u8 = 34;
+pub const EVENT_INPUT_GAMEPAD_PRESSED_DOWN: u8 = 35;
+pub const EVENT_INPUT_GAMEPAD_PRESSED_LEFT: u8 = 36;
+pub const EVENT_INPUT_GAMEPAD_PRESSED_RIGHT: u8 = 37;
 
 pub const EVENT_INPUT_KEYBOARD_PRESSED_ESCAPE: u8 = 20;
 pub const EVENT_INPUT_KEYBOARD_PRESSED_UP: u8 = 21;
@@ -82,6 +89,7 @@ pub const EVENT_INPUT_KEYBOARD_PRESSED_LSHIFT: u8 = 29;
 pub const EVENT_INPUT_KEYBOARD_PRESSED_PLUS: u8 = 30;
 pub const EVENT_INPUT_KEYBOARD_PRESSED_MINUS: u8 = 31;
 pub const EVENT_INPUT_KEYBOARD_PRESSED_LALT: u8 = 32;
+pub const EVENT_INPUT_KEYBOARD_PRESSED_RETURN: u8 = 33;
 
 pub const EVENT_INPUT_KEYBOARD_PRESSED_0: u8 = 48;
 pub const EVENT_INPUT_KEYBOARD_PRESSED_9: u8 = 57;
@@ -173,6 +181,7 @@ pub struct Core {
     easing_system: std::boxed::Box<ecs::EasingSystem>,
     events: std::boxed::Box<VecDeque<u8>>,
     gamepad:std::boxed::Box<Gilrs>,
+    replay: std::boxed::Box<replay::Replay>,
 }
 
 impl Core {
@@ -272,6 +281,7 @@ impl Core {
         let events = Box::new(VecDeque::new());
         //let mut gilrs = Gilrs::new().unwrap();
         let gamepad = Box::new(Gilrs::new().unwrap());
+        let replay = Box::new(replay::Replay::new());
 
 
         cache.print_loading();
@@ -288,6 +298,24 @@ impl Core {
             easing_system,
             events,
             gamepad,
+            replay,
+        }
+    }
+
+    // write every consumed input to a replay file, see replay::Replay
+    pub fn record(&mut self, filename: &str) {
+        match replay::Replay::record(filename) {
+            Ok(replay) => *self.replay = replay,
+            Err(err) => println!("{filename}: {err}, not recording"),
+        }
+    }
+
+    // feed a recorded session back in place of live input, live input
+    // takes over again once it runs out
+    pub fn replay(&mut self, filename: &str) {
+        match replay::Replay::load(filename) {
+            Ok(replay) => *self.replay = replay,
+            Err(err) => println!("{filename}: {err}, not replaying"),
         }
     }
 
@@ -307,6 +335,7 @@ impl Core {
                 }
                 WindowEvent::Resized(physical_size) => self.windowed_context.resize(*physical_size),
                 WindowEvent::CloseRequested => cache.quit_requested = true,
+                WindowEvent::CursorMoved { .. } | WindowEvent::MouseInput { .. } | WindowEvent::KeyboardInput { .. } if self.replay.is_playing() => (),
                 WindowEvent::CursorMoved { position, .. } => {
                     self.update_mouse_xy(
                         (position.x / (cache.scale_factor * WINDOW_SCALE)) as i32,
@@ -331,6 +360,9 @@ impl Core {
 
         // check for gamepad events
         self.update_gamepad_input();
+        if self.replay.is_playing() {
+            self.events.clear();
+        }
 
         cache.quit_requested |= self.game.quit_requested();
  
@@ -353,10 +385,16 @@ impl Core {
                     match axis {
                         gilrs::Axis::LeftStickX => {
                             self.events.push_back(EVENT_INPUT_GAMEPAD_AXIS_MOVEMENT);
+                            if 0.5 > self.world.gamepad_x.abs() && 0.5 <= offset.abs() {
+                                self.events.push_back(if 0.0 < offset { EVENT_INPUT_GAMEPAD_PRESSED_RIGHT } else { EVENT_INPUT_GAMEPAD_PRESSED_LEFT });
+                            }
                             self.world.gamepad_x = offset;
                         }
                         gilrs::Axis::LeftStickY => {
                             self.events.push_back(EVENT_INPUT_GAMEPAD_AXIS_MOVEMENT);
+                            if 0.5 > self.world.gamepad_y.abs() && 0.5 <= offset.abs() {
+                                self.events.push_back(if 0.0 < offset { EVENT_INPUT_GAMEPAD_PRESSED_UP } else { EVENT_INPUT_GAMEPAD_PRESSED_DOWN });
+                            }
                             self.world.gamepad_y = offset;
                         }
                         _ => (),
@@ -378,6 +416,18 @@ impl Core {
                         gilrs::Button::West => {
                             self.events.push_back(EVENT_INPUT_GAMEPAD_PRESSED_X);
                         }
+                        gilrs::Button::DPadUp => {
+                            self.events.push_back(EVENT_INPUT_GAMEPAD_PRESSED_UP);
+                        }
+                        gilrs::Button::DPadDown => {
+                            self.events.push_back(EVENT_INPUT_GAMEPAD_PRESSED_DOWN);
+                        }
+                        gilrs::Button::DPadLeft => {
+                            self.events.push_back(EVENT_INPUT_GAMEPAD_PRESSED_LEFT);
+                        }
+                        gilrs::Button::DPadRight => {
+                            self.events.push_back(EVENT_INPUT_GAMEPAD_PRESSED_RIGHT);
+                        }
                         _ => (),
                     }
                 },
@@ -411,6 +461,9 @@ impl Core {
                 Some(VirtualKeyCode::Escape) => {
                     self.events.push_back(EVENT_INPUT_KEYBOARD_PRESSED_ESCAPE)
                 }
+                Some(VirtualKeyCode::Return) => {
+                    self.events.push_back(EVENT_INPUT_KEYBOARD_PRESSED_RETURN)
+                }
                 Some(VirtualKeyCode::Up) => self.events.push_back(EVENT_INPUT_KEYBOARD_PRESSED_UP),
                 Some(VirtualKeyCode::Down) => {
                     self.events.push_back(EVENT_INPUT_KEYBOARD_PRESSED_DOWN)
@@ -730,7 +783,17 @@ impl Core {
     fn initialize(&mut self) {
         let cache = unsafe { &mut *(self.data.offset(0)) };
 
+        if let Some(seed) = self.replay.seed() {
+            self.world.set_seed(seed);
+        }
+        if self.replay.is_recording() || self.replay.is_playing() {
+            self.world.start_recorded_session(self.replay.pins());
+        }
         self.game.initialize(&mut self.world);
+        if self.replay.is_recording() {
+            self.replay.record_seed(self.world.seed());
+        }
+        self.record_pins();
         cache.initialized = true;
         let ms = std::time::Instant::now()
             .duration_since(cache.start_time)
@@ -748,6 +811,13 @@ impl Core {
         }
     }
 
+    // values the game pinned go in the recording where they were taken
+    fn record_pins(&mut self) {
+        for (key, value) in self.world.take_pins() {
+            self.replay.record_pin(&key, &value);
+        }
+    }
+
     pub fn ready_to_quit(&mut self) -> bool {
         let cache = unsafe { &mut *(self.data.offset(0)) };
         cache.ready_to_quit
@@ -762,6 +832,7 @@ impl Core {
         let cache = unsafe { &mut *(self.data.offset(0)) };
 
         self.game.shutdown();
+        self.replay.finish();
 
         if 0 < cache.blown_update_frames_significant {
             log(format!(
@@ -832,8 +903,19 @@ impl Core {
 
             let mut expect_blown = false;
 
+            // recorded input goes in at the tick it was taken
+            let tick = cache.count_update_frames;
+            if self.replay.is_playing() {
+                if let Some((x, y)) = self.replay.play_mouse(tick) {
+                    self.update_mouse_xy(x, y);
+                }
+            } else {
+                self.replay.record_mouse(tick, self.world.mouse_x, self.world.mouse_y);
+            }
+
             // update game
             expect_blown |= self.game.update(&mut self.world, UPDATE_DT);
+            self.record_pins();
 
             // update systems
             if 0 == cache.count_update_frames % 1 {
@@ -852,10 +934,25 @@ impl Core {
                 expect_blown |= self.physics_system.update(&mut self.world, UPDATE_DT * 4);
                 cache.last_physics = std::time::Instant::now();
 
-                if let Some(val) = self.events.pop_front() {
+                let event = match self.replay.is_playing() {
+                    true => self.replay.play_event(tick).map(|entry| {
+                        self.update_mouse_xy(entry.x, entry.y);
+                        (entry.event, entry.actions)
+                    }),
+                    false => self.events.pop_front().map(|val| (val, None)),
+                };
+
+                // a replay brings the actions as they were mapped when it was recorded
+                if let Some((val, actions)) = event {
+                    match actions {
+                        Some(state) => self.world.input.set_state(state),
+                        None => self.world.input.event(val),
+                    }
+                    self.replay.record_event(tick, val, self.world.mouse_x, self.world.mouse_y, self.world.input.state());
                     expect_blown |= self.world.mgui.event(self.world.mouse_x, self.world.mouse_y, val);
                     expect_blown |= self.world.ugui.event(self.world.mouse_x, self.world.mouse_y, val);
                     expect_blown |= self.game.event(&mut self.world, val);
+                    self.record_pins();
                 }
 
                 expect_blown |= self.easing_system.update(&mut self.world, UPDATE_DT * 4);
@@ -0,0 +1,167 @@
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufRead, BufWriter, Write};
+
+use super::EVENT_INVALID;
+
+// one recorded input, EVENT_INVALID marks a plain mouse move
+#[derive(Clone, Copy)]
+pub struct ReplayEntry {
+    pub tick: usize,
+    pub event: u8,
+    pub x: i32,
+    pub y: i32,
+    pub actions: Option<[u64; 4]>, // input map state after the event, see InputMap::state
+}
+
+// input recording and playback, keyed by update tick. with the same seed the
+// same input gives the same session. events carry the actions they mapped to
+// so local bindings don't matter, and values the game takes from outside the
+// session are pinned, see World::pin. file lines are seed,<seed>, then
+// mouse,<tick>,<x>,<y>, event,<tick>,<id>,<x>,<y>,<pressed>,<repeated>,<released>,<held>
+// and pin,<key>,<value> in the order they were taken
+pub struct Replay {
+    recording: Option<BufWriter<File>>,
+    playing: VecDeque<ReplayEntry>,
+    pins: Vec<(String, String)>,
+    seed: Option<u64>,
+    mouse_x: i32,
+    mouse_y: i32,
+}
+
+#[allow(dead_code)]
+impl Replay {
+    pub fn new() -> Replay {
+        Replay {
+            recording: None,
+            playing: VecDeque::new(),
+            pins: Vec::new(),
+            seed: None,
+            mouse_x: -1,
+            mouse_y: -1,
+        }
+    }
+
+    pub fn record(filename: &str) -> io::Result<Replay> {
+        let mut replay = Replay::new();
+        replay.recording = Some(BufWriter::new(File::create(filename)?));
+        Ok(replay)
+    }
+
+    pub fn load(filename: &str) -> io::Result<Replay> {
+        let mut replay = Replay::new();
+
+        let reader = io::BufReader::new(File::open(filename)?);
+        for line in reader.lines() {
+            let line = line?;
+            if let Some((key, value)) = line.strip_prefix("pin,").and_then(|v| v.split_once(',')) {
+                replay.pins.push((String::from(key), String::from(value)));
+                continue;
+            }
+
+            let split: Vec<&str> = line.trim().split(',').collect();
+            let field = |i: usize| split.get(i).and_then(|v| v.parse::<i64>().ok());
+            let mask = |i: usize| split.get(i).and_then(|v| v.parse::<u64>().ok());
+            let actions = match (mask(5), mask(6), mask(7), mask(8)) {
+                (Some(p), Some(r), Some(l), Some(h)) => Some([p, r, l, h]),
+                _ => None,
+            };
+
+            match (split[0], field(1), field(2), field(3), field(4)) {
+                ("seed", _, _, _, _) => replay.seed = split.get(1).and_then(|v| v.parse::<u64>().ok()),
+                ("mouse", Some(tick), Some(x), Some(y), _) => replay.playing.push_back(ReplayEntry {
+                    tick: tick as usize,
+                    event: EVENT_INVALID,
+                    x: x as i32,
+                    y: y as i32,
+                    actions: None,
+                }),
+                ("event", Some(tick), Some(event), Some(x), Some(y)) => replay.playing.push_back(ReplayEntry {
+                    tick: tick as usize,
+                    event: event as u8,
+                    x: x as i32,
+                    y: y as i32,
+                    actions,
+                }),
+                _ => (),
+            }
+        }
+
+        Ok(replay)
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    pub fn is_playing(&self) -> bool {
+        !self.playing.is_empty()
+    }
+
+    // world seed the session was recorded with
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    pub fn record_seed(&mut self, seed: u64) {
+        self.write(format!("seed,{seed}"));
+    }
+
+    // values the recording pinned, for World::start_recorded_session
+    pub fn pins(&self) -> Vec<(String, String)> {
+        self.pins.clone()
+    }
+
+    pub fn record_pin(&mut self, key: &str, value: &str) {
+        self.write(format!("pin,{key},{value}"));
+    }
+
+    // only moves are written, a still mouse costs nothing
+    pub fn record_mouse(&mut self, tick: usize, x: i32, y: i32) {
+        if x == self.mouse_x && y == self.mouse_y { return; }
+        self.mouse_x = x;
+        self.mouse_y = y;
+        self.write(format!("mouse,{tick},{x},{y}"));
+    }
+
+    pub fn record_event(&mut self, tick: usize, event: u8, x: i32, y: i32, actions: [u64; 4]) {
+        let [p, r, l, h] = actions;
+        self.write(format!("event,{tick},{event},{x},{y},{p},{r},{l},{h}"));
+    }
+
+    // mouse position as of this tick, if it moved
+    pub fn play_mouse(&mut self, tick: usize) -> Option<(i32, i32)> {
+        let mut pos = None;
+        while let Some(entry) = self.playing.front() {
+            if entry.tick > tick || EVENT_INVALID != entry.event { break; }
+            pos = Some((entry.x, entry.y));
+            self.playing.pop_front();
+        }
+        pos
+    }
+
+    // the event consumed on this tick, if there was one
+    pub fn play_event(&mut self, tick: usize) -> Option<ReplayEntry> {
+        match self.playing.front() {
+            Some(entry) if entry.tick <= tick && EVENT_INVALID != entry.event => self.playing.pop_front(),
+            _ => None,
+        }
+    }
+
+    pub fn finish(&mut self) {
+        if let Some(file) = &mut self.recording {
+            if let Err(err) = file.flush() {
+                println!("replay not saved: {err}");
+            }
+        }
+        self.recording = None;
+    }
+
+    fn write(&mut self, line: String) {
+        if let Some(file) = &mut self.recording {
+            if writeln!(file, "{line}").is_err() {
+                self.recording = None;
+            }
+        }
+    }
+}